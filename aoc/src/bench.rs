@@ -0,0 +1,421 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use color_eyre::eyre::{self, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// Bench every day that has an input instead of a single one
+    #[arg(long)]
+    pub all: bool,
+    /// Save the median times under this name
+    #[arg(long)]
+    pub save_baseline: Option<String>,
+    /// Compare the median times against a saved baseline
+    #[arg(long)]
+    pub compare: Option<String>,
+    /// Slowdown, in percent, above which a benchmark is a regression
+    #[arg(long, default_value_t = 5.0)]
+    pub threshold: f64,
+    #[arg(long, value_enum, default_value_t = Format::Markdown)]
+    pub format: Format,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum Format {
+    Markdown,
+    Json,
+}
+
+/// Median time in nanoseconds of each benchmark id
+type Medians = BTreeMap<String, f64>;
+
+#[derive(Deserialize)]
+struct Estimate {
+    estimate: f64,
+    unit: String,
+}
+
+/// The subset of cargo-criterion's `--message-format=json` messages we use
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum Message {
+    BenchmarkComplete {
+        id: String,
+        median: Estimate,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Row {
+    id: String,
+    /// `None` for benchmarks only in the baseline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    median_ns: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    baseline_ns: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change_percent: Option<f64>,
+    regression: bool,
+}
+
+fn to_ns(estimate: &Estimate) -> color_eyre::Result<f64> {
+    let factor = match estimate.unit.as_str() {
+        "ns" => 1.,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        unit => eyre::bail!("Unknown time unit '{unit}'"),
+    };
+
+    Ok(estimate.estimate * factor)
+}
+
+fn format_ns(ns: f64) -> String {
+    match ns {
+        ns if ns < 1e3 => format!("{ns:.2} ns"),
+        ns if ns < 1e6 => format!("{:.2} µs", ns / 1e3),
+        ns if ns < 1e9 => format!("{:.2} ms", ns / 1e6),
+        ns => format!("{:.2} s", ns / 1e9),
+    }
+}
+
+fn baseline_path(workspace: &Path, name: &str) -> PathBuf {
+    workspace
+        .join("target/aoc-baselines")
+        .join(format!("{name}.json"))
+}
+
+/// Medians of the benchmarks completed in cargo-criterion's json messages
+fn parse_medians(messages: impl BufRead) -> color_eyre::Result<Medians> {
+    let mut medians = Medians::new();
+    for line in messages.lines() {
+        let line = line?;
+        // cargo-criterion still prints some human readable lines
+        let Ok(message) = serde_json::from_str::<Message>(&line) else {
+            continue;
+        };
+
+        if let Message::BenchmarkComplete { id, median } = message {
+            let median = to_ns(&median)?;
+            eprintln!("{id}: {}", format_ns(median));
+            medians.insert(id, median);
+        }
+    }
+
+    Ok(medians)
+}
+
+/// Runs the benchmarks and collects the median of each benchmark
+fn collect_medians(mut command: std::process::Command) -> color_eyre::Result<Medians> {
+    let mut child = command.stdout(Stdio::piped()).spawn()?;
+
+    let medians = parse_medians(BufReader::new(child.stdout.take().unwrap()))?;
+
+    if !child.wait()?.success() {
+        eyre::bail!("Criterion returned an error")
+    }
+
+    Ok(medians)
+}
+
+/// Compares `medians` with `baseline`, including the benchmarks missing from either
+fn compare(medians: &Medians, baseline: Option<&Medians>, threshold: f64) -> Vec<Row> {
+    let removed = baseline
+        .into_iter()
+        .flatten()
+        .filter(|(id, _)| !medians.contains_key(*id))
+        .map(|(id, &baseline_ns)| Row {
+            id: id.clone(),
+            median_ns: None,
+            baseline_ns: Some(baseline_ns),
+            change_percent: None,
+            regression: false,
+        });
+
+    medians
+        .iter()
+        .map(|(id, &median_ns)| {
+            let baseline_ns = baseline.and_then(|b| b.get(id)).copied();
+            let change_percent = baseline_ns.map(|base| (median_ns - base) / base * 100.);
+            Row {
+                id: id.clone(),
+                median_ns: Some(median_ns),
+                baseline_ns,
+                change_percent,
+                regression: change_percent.is_some_and(|c| c > threshold),
+            }
+        })
+        .chain(removed)
+        .collect()
+}
+
+fn format_rows(rows: &[Row], format: Format, compared: bool) -> color_eyre::Result<String> {
+    let median = |row: &Row| row.median_ns.map_or("-".into(), format_ns);
+
+    let mut out = String::new();
+    match format {
+        Format::Json => out = serde_json::to_string_pretty(rows)?,
+        Format::Markdown if compared => {
+            out += "| Benchmark | Baseline | Median | Change |\n";
+            out += "|-----------|---------:|-------:|-------:|\n";
+            for row in rows {
+                let baseline = row.baseline_ns.map_or("-".into(), format_ns);
+                let change = match (row.change_percent, row.median_ns) {
+                    (None, None) => "removed".into(),
+                    (None, Some(_)) => "new".into(),
+                    (Some(change), _) if row.regression => format!("**{change:+.2}%** ⚠"),
+                    (Some(change), _) => format!("{change:+.2}%"),
+                };
+                out += &format!("| {} | {baseline} | {} | {change} |\n", row.id, median(row));
+            }
+        }
+        Format::Markdown => {
+            out += "| Benchmark | Median |\n";
+            out += "|-----------|-------:|\n";
+            for row in rows {
+                out += &format!("| {} | {} |\n", row.id, median(row));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn report(workspace: &Path, args: &BenchArgs, medians: &Medians) -> color_eyre::Result<()> {
+    let baseline: Option<Medians> = match &args.compare {
+        None => None,
+        Some(name) => {
+            let path = baseline_path(workspace, name);
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read baseline '{name}' from {path:?}"))?;
+            Some(serde_json::from_str(&content)?)
+        }
+    };
+
+    let rows = compare(medians, baseline.as_ref(), args.threshold);
+    println!(
+        "{}",
+        format_rows(&rows, args.format, baseline.is_some())?.trim_end()
+    );
+    if let Some(name) = &args.save_baseline {
+        let path = baseline_path(workspace, name);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, serde_json::to_string_pretty(medians)?)?;
+        eprintln!("==> Saved baseline '{name}' to {path:?}");
+    }
+
+    let regressions = rows.iter().filter(|row| row.regression).count();
+    if regressions != 0 {
+        eyre::bail!(
+            "{regressions} benchmarks regressed by more than {}%",
+            args.threshold
+        );
+    }
+
+    Ok(())
+}
+
+/// Benches a single day on `input`, or every day if `day` is `None`
+pub fn run(
+    config: &Config,
+    day: Option<(u32, &Path)>,
+    args: &BenchArgs,
+    criterion_args: &[String],
+) -> color_eyre::Result<()> {
+    let mut command = std::process::Command::new(env!("CARGO"));
+    command.current_dir(config.workspace.join("problems"));
+
+    match day {
+        Some((day, input)) => {
+            eprintln!("==> Benching day {day}");
+            command
+                .env("AOC_DAY", day.to_string())
+                .env("AOC_INPUT", input);
+        }
+        None => {
+            eprintln!("==> Benching all days");
+            command
+                .env("AOC_INPUTS", &config.inputs)
+                .env_remove("AOC_INPUT");
+        }
+    }
+
+    command.args(["criterion", "--bench", "days"]);
+
+    let summarize = args.save_baseline.is_some() || args.compare.is_some();
+    if summarize {
+        command.arg("--message-format=json");
+    }
+
+    command.args(&config.criterion_args).args(criterion_args);
+
+    if !summarize {
+        return Err(command.exec().into());
+    }
+
+    let medians = collect_medians(command)?;
+    report(&config.workspace, args, &medians)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compare, format_rows, parse_medians, Format, Medians, Row};
+
+    /// Captured from `cargo criterion --message-format=json`, trimmed of the measurements
+    const MESSAGES: &str = r#"Gnuplot not found, using plotters backend
+{"reason":"benchmark-complete","id":"parsing/day1","report_directory":"target/criterion/reports/parsing/day1","iteration_count":[1,2],"measured_values":[1,2],"unit":"ns","throughput":[],"typical":{"estimate":812.4,"lower_bound":800.1,"upper_bound":820.9,"unit":"ns"},"mean":{"estimate":812.4,"lower_bound":800.1,"upper_bound":820.9,"unit":"ns"},"median":{"estimate":810.0,"lower_bound":805.2,"upper_bound":815.3,"unit":"ns"},"median_abs_dev":{"estimate":3.1,"lower_bound":2.0,"upper_bound":4.2,"unit":"ns"},"slope":null,"change":null}
+{"reason":"group-complete","group_name":"parsing","benchmarks":["parsing/day1"],"report_directory":"target/criterion/reports/parsing"}
+{"reason":"benchmark-complete","id":"part1/day1","report_directory":"target/criterion/reports/part1/day1","iteration_count":[1,2],"measured_values":[1,2],"unit":"ns","throughput":[],"typical":{"estimate":2.5,"lower_bound":2.4,"upper_bound":2.6,"unit":"us"},"mean":{"estimate":2.5,"lower_bound":2.4,"upper_bound":2.6,"unit":"us"},"median":{"estimate":2.5,"lower_bound":2.4,"upper_bound":2.6,"unit":"µs"},"median_abs_dev":{"estimate":0.1,"lower_bound":0.0,"upper_bound":0.2,"unit":"us"},"slope":null,"change":null}
+{"reason":"benchmark-complete","id":"part2/day1","report_directory":"target/criterion/reports/part2/day1","iteration_count":[1,2],"measured_values":[1,2],"unit":"ns","throughput":[],"typical":{"estimate":1.25,"lower_bound":1.2,"upper_bound":1.3,"unit":"ms"},"mean":{"estimate":1.25,"lower_bound":1.2,"upper_bound":1.3,"unit":"ms"},"median":{"estimate":1.25,"lower_bound":1.2,"upper_bound":1.3,"unit":"ms"},"median_abs_dev":{"estimate":0.01,"lower_bound":0.0,"upper_bound":0.02,"unit":"ms"},"slope":null,"change":null}
+part2/day1              time:   [1.2000 ms 1.2500 ms 1.3000 ms]
+{"reason":"benchmark-complete","id":"part2/day7","report_directory":"target/criterion/reports/part2/day7","iteration_count":[1,2],"measured_values":[1,2],"unit":"ns","throughput":[],"typical":{"estimate":2.0,"lower_bound":1.9,"upper_bound":2.1,"unit":"s"},"mean":{"estimate":2.0,"lower_bound":1.9,"upper_bound":2.1,"unit":"s"},"median":{"estimate":2.0,"lower_bound":1.9,"upper_bound":2.1,"unit":"s"},"median_abs_dev":{"estimate":0.1,"lower_bound":0.0,"upper_bound":0.2,"unit":"s"},"slope":null,"change":null}
+"#;
+
+    fn medians(entries: &[(&str, f64)]) -> Medians {
+        entries
+            .iter()
+            .map(|&(id, median)| (id.to_string(), median))
+            .collect()
+    }
+
+    #[test]
+    fn medians_in_nanoseconds() {
+        assert_eq!(
+            parse_medians(MESSAGES.as_bytes()).unwrap(),
+            medians(&[
+                ("parsing/day1", 810.),
+                ("part1/day1", 2_500.),
+                ("part2/day1", 1_250_000.),
+                ("part2/day7", 2e9),
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_unit() {
+        let message = r#"{"reason":"benchmark-complete","id":"part1/day1","median":{"estimate":1.0,"unit":"min"}}"#;
+        assert!(parse_medians(message.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn threshold() {
+        let baseline = medians(&[("part1/day1", 100.), ("part2/day1", 100.)]);
+        let current = medians(&[("part1/day1", 104.9), ("part2/day1", 105.1)]);
+
+        let regressions: Vec<_> = compare(&current, Some(&baseline), 5.)
+            .into_iter()
+            .map(|row| (row.id, row.regression))
+            .collect();
+        assert_eq!(
+            regressions,
+            [("part1/day1".into(), false), ("part2/day1".into(), true)]
+        );
+
+        assert!(compare(&current, Some(&baseline), 6.)
+            .iter()
+            .all(|row| !row.regression));
+    }
+
+    #[test]
+    fn missing_benchmarks() {
+        let baseline = medians(&[("part1/day1", 100.), ("part1/day2", 100.)]);
+        let current = medians(&[("part1/day1", 50.), ("part1/day3", 1000.)]);
+
+        assert_eq!(
+            compare(&current, Some(&baseline), 5.),
+            [
+                Row {
+                    id: "part1/day1".into(),
+                    median_ns: Some(50.),
+                    baseline_ns: Some(100.),
+                    change_percent: Some(-50.),
+                    regression: false,
+                },
+                Row {
+                    id: "part1/day3".into(),
+                    median_ns: Some(1000.),
+                    baseline_ns: None,
+                    change_percent: None,
+                    regression: false,
+                },
+                Row {
+                    id: "part1/day2".into(),
+                    median_ns: None,
+                    baseline_ns: Some(100.),
+                    change_percent: None,
+                    regression: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let current = medians(&[("part1/day1", 2_500.)]);
+        assert_eq!(
+            format_rows(&compare(&current, None, 5.), Format::Markdown, false).unwrap(),
+            "| Benchmark | Median |\n\
+             |-----------|-------:|\n\
+             | part1/day1 | 2.50 µs |\n"
+        );
+
+        let baseline = medians(&[
+            ("part1/day1", 2_000.),
+            ("part1/day2", 3e6),
+            ("part2/day1", 100.),
+        ]);
+        let current = medians(&[
+            ("part1/day1", 2_500.),
+            ("part2/day1", 101.),
+            ("part2/day2", 4e9),
+        ]);
+        assert_eq!(
+            format_rows(
+                &compare(&current, Some(&baseline), 5.),
+                Format::Markdown,
+                true
+            )
+            .unwrap(),
+            "| Benchmark | Baseline | Median | Change |\n\
+             |-----------|---------:|-------:|-------:|\n\
+             | part1/day1 | 2.00 µs | 2.50 µs | **+25.00%** ⚠ |\n\
+             | part2/day1 | 100.00 ns | 101.00 ns | +1.00% |\n\
+             | part2/day2 | - | 4.00 s | new |\n\
+             | part1/day2 | 3.00 ms | - | removed |\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let baseline = medians(&[("part1/day1", 2_000.), ("part1/day2", 3e6)]);
+        let current = medians(&[("part1/day1", 2_500.), ("part2/day2", 4e9)]);
+        let json =
+            format_rows(&compare(&current, Some(&baseline), 5.), Format::Json, true).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!([
+                {
+                    "id": "part1/day1",
+                    "median_ns": 2500.0,
+                    "baseline_ns": 2000.0,
+                    "change_percent": 25.0,
+                    "regression": true
+                },
+                { "id": "part2/day2", "median_ns": 4e9, "regression": false },
+                { "id": "part1/day2", "baseline_ns": 3e6, "regression": false }
+            ])
+        );
+    }
+}
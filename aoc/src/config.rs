@@ -0,0 +1,302 @@
+//! Runner configuration.
+//!
+//! Settings are looked up in this order, the first one found wins:
+//!  1. command line flags (`--year`, `--release`, `--cookie`)
+//!  2. `AOC_*` environment variables: `AOC_YEAR`, `AOC_INPUTS`, `AOC_RELEASE`,
+//!     `AOC_CRITERION_ARGS` (split on whitespace), `AOC_EDITOR`,
+//!     `AOC_FLAMEGRAPH_VIEWER` and `AOC_COOKIE` for the session
+//!  3. the project `aoc.toml`, found by walking up from the current directory
+//!  4. the user configuration in `$XDG_CONFIG_HOME/aoc/config.toml`
+//!  5. generic environment variables (`$EDITOR`) and built-in defaults
+//!
+//! Relative paths are relative to the workspace.
+
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, Context};
+use serde::Deserialize;
+
+pub const PROJECT_CONFIG: &str = "aoc.toml";
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "source", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SessionSource {
+    /// Read the session cookie from an environment variable
+    Env { var: String },
+    /// Read the session cookie from a file
+    File { path: PathBuf },
+    /// Read the session cookie from the secret service with `secret-tool`
+    Keyring {
+        #[serde(default = "default_keyring_service")]
+        service: String,
+        #[serde(default = "default_keyring_user")]
+        user: String,
+    },
+}
+
+fn default_keyring_service() -> String {
+    "adventofcode.com".into()
+}
+
+fn default_keyring_user() -> String {
+    "session".into()
+}
+
+impl SessionSource {
+    pub fn read(&self, workspace: &Path) -> color_eyre::Result<String> {
+        let cookie = match self {
+            SessionSource::Env { var } => {
+                std::env::var(var).with_context(|| format!("Could not read session from ${var}"))?
+            }
+            SessionSource::File { path } => {
+                let path = workspace.join(expand_home(path));
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("Could not read session from {path:?}"))?
+            }
+            SessionSource::Keyring { service, user } => {
+                let output = std::process::Command::new("secret-tool")
+                    .args(["lookup", "service", service, "user", user])
+                    .output()
+                    .context("Could not run secret-tool")?;
+                if !output.status.success() {
+                    eyre::bail!("No session found in the keyring for {service}/{user}");
+                }
+                String::from_utf8(output.stdout).context("Session in keyring is not utf-8")?
+            }
+        };
+
+        Ok(cookie.trim().to_string())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Viewers {
+    pub editor: Option<String>,
+    pub flamegraph: Option<String>,
+}
+
+/// Contents of a single configuration file, every setting is optional
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub year: Option<i32>,
+    pub inputs: Option<PathBuf>,
+    pub release: Option<bool>,
+    pub criterion_args: Option<Vec<String>>,
+    pub session: Option<SessionSource>,
+    #[serde(default)]
+    pub viewers: Viewers,
+}
+
+impl ConfigFile {
+    fn load(path: &Path) -> color_eyre::Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("Invalid configuration in {path:?}"))
+                .map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Could not read {path:?}")),
+        }
+    }
+
+    /// Settings given by the `AOC_*` variables, as returned by `var`
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> color_eyre::Result<Self> {
+        Ok(Self {
+            year: parse_var("AOC_YEAR", var("AOC_YEAR"))?,
+            inputs: var("AOC_INPUTS").map(PathBuf::from),
+            release: parse_var("AOC_RELEASE", var("AOC_RELEASE"))?,
+            criterion_args: var("AOC_CRITERION_ARGS")
+                .map(|args| args.split_whitespace().map(String::from).collect()),
+            session: None,
+            viewers: Viewers {
+                editor: var("AOC_EDITOR"),
+                flamegraph: var("AOC_FLAMEGRAPH_VIEWER"),
+            },
+        })
+    }
+
+    /// Fill the settings missing in `self` from `fallback`
+    fn or(self, fallback: Self) -> Self {
+        Self {
+            year: self.year.or(fallback.year),
+            inputs: self.inputs.or(fallback.inputs),
+            release: self.release.or(fallback.release),
+            criterion_args: self.criterion_args.or(fallback.criterion_args),
+            session: self.session.or(fallback.session),
+            viewers: Viewers {
+                editor: self.viewers.editor.or(fallback.viewers.editor),
+                flamegraph: self.viewers.flamegraph.or(fallback.viewers.flamegraph),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub workspace: PathBuf,
+    pub year: i32,
+    pub inputs: PathBuf,
+    pub release: bool,
+    pub criterion_args: Vec<String>,
+    pub session: Option<SessionSource>,
+    pub editor: Option<String>,
+    pub flamegraph_viewer: Option<String>,
+}
+
+fn parse_var<T: std::str::FromStr>(
+    name: &str,
+    value: Option<String>,
+) -> color_eyre::Result<Option<T>> {
+    value
+        .map(|v| v.parse().map_err(|_| eyre::eyre!("Invalid ${name}: {v:?}")))
+        .transpose()
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => path.to_owned(),
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config_dir.join("aoc/config.toml"))
+}
+
+/// The closest parent of the current directory holding an `aoc.toml`, or the
+/// workspace the runner was built from
+fn find_workspace() -> PathBuf {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| {
+            cwd.ancestors()
+                .find(|dir| dir.join(PROJECT_CONFIG).is_file())
+                .map(Path::to_owned)
+        })
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .parent()
+                .unwrap()
+                .to_owned()
+        })
+}
+
+impl Config {
+    /// Loads the configuration, with the settings of `cli` taking precedence
+    pub fn load(cli: ConfigFile) -> color_eyre::Result<Self> {
+        let workspace = find_workspace();
+
+        let project = ConfigFile::load(&workspace.join(PROJECT_CONFIG))?.unwrap_or_default();
+        let user = match user_config_path() {
+            Some(path) => ConfigFile::load(&path)?.unwrap_or_default(),
+            None => ConfigFile::default(),
+        };
+        let env = ConfigFile::from_env(|var| std::env::var(var).ok())?;
+        let file = cli.or(env).or(project).or(user);
+
+        Ok(Self {
+            year: file.year.unwrap_or(2022),
+            inputs: workspace.join(expand_home(
+                &file.inputs.unwrap_or_else(|| PathBuf::from("inputs")),
+            )),
+            release: file.release.unwrap_or(false),
+            criterion_args: file.criterion_args.unwrap_or_default(),
+            session: file.session,
+            editor: file.viewers.editor.or_else(|| std::env::var("EDITOR").ok()),
+            flamegraph_viewer: file.viewers.flamegraph,
+            workspace,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{ConfigFile, SessionSource};
+
+    fn parse(content: &str) -> ConfigFile {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn or() {
+        let project = parse("year = 2021\n[viewers]\neditor = \"vim\"\n");
+        let user = parse(
+            "year = 2020\nrelease = true\n[viewers]\neditor = \"nano\"\nflamegraph = \"firefox\"\n",
+        );
+        let config = ConfigFile::default().or(project).or(user);
+
+        assert_eq!(config.year, Some(2021));
+        assert_eq!(config.release, Some(true));
+        assert_eq!(config.inputs, None);
+        assert_eq!(config.viewers.editor.as_deref(), Some("vim"));
+        assert_eq!(config.viewers.flamegraph.as_deref(), Some("firefox"));
+    }
+
+    #[test]
+    fn env() {
+        let env = ConfigFile::from_env(|var| match var {
+            "AOC_YEAR" => Some("2019".into()),
+            "AOC_INPUTS" => Some("/tmp/inputs".into()),
+            "AOC_CRITERION_ARGS" => Some(" --noplot  --quick".into()),
+            "AOC_EDITOR" => Some("hx".into()),
+            _ => None,
+        })
+        .unwrap();
+        let cli = ConfigFile {
+            release: Some(false),
+            ..Default::default()
+        };
+        let config = cli
+            .or(env)
+            .or(parse("year = 2022\nrelease = true\ncriterion_args = []\n"));
+
+        assert_eq!(config.year, Some(2019));
+        assert_eq!(config.inputs, Some(PathBuf::from("/tmp/inputs")));
+        assert_eq!(config.release, Some(false));
+        assert_eq!(
+            config.criterion_args,
+            Some(vec!["--noplot".into(), "--quick".into()])
+        );
+        assert_eq!(config.viewers.editor.as_deref(), Some("hx"));
+
+        let err = ConfigFile::from_env(|var| (var == "AOC_RELEASE").then(|| "yes".into()));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Invalid $AOC_RELEASE: \"yes\""
+        );
+    }
+
+    #[test]
+    fn session() {
+        let session = |content: &str| parse(content).session.unwrap();
+
+        assert!(matches!(
+            session("[session]\nsource = \"env\"\nvar = \"AOC_SESSION\"\n"),
+            SessionSource::Env { var } if var == "AOC_SESSION"
+        ));
+        assert!(matches!(
+            session("[session]\nsource = \"file\"\npath = \"~/.config/aoc/session\"\n"),
+            SessionSource::File { path } if path == Path::new("~/.config/aoc/session")
+        ));
+        assert!(matches!(
+            session("[session]\nsource = \"keyring\"\n"),
+            SessionSource::Keyring { service, user }
+                if (service.as_str(), user.as_str()) == ("adventofcode.com", "session")
+        ));
+        assert!(matches!(
+            session("[session]\nsource = \"keyring\"\nuser = \"me\"\n"),
+            SessionSource::Keyring { user, .. } if user == "me"
+        ));
+
+        assert!(toml::from_str::<ConfigFile>("[session]\nsource = \"cookie\"\n").is_err());
+        assert!(toml::from_str::<ConfigFile>("[session]\nsource = \"env\"\n").is_err());
+        assert!(parse("").session.is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, Context};
+
+/// Replaces every `{{ variable }}` in `template`, failing on unknown variables
+fn render(template: &str, variables: &[(&str, String)]) -> color_eyre::Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+
+        let Some(len) = rest[start..].find("}}") else {
+            eyre::bail!("Unterminated template variable");
+        };
+        let name = rest[start + 2..start + len].trim();
+
+        let Some((_, value)) = variables.iter().find(|(var, _)| *var == name) else {
+            eyre::bail!("Unknown template variable '{name}'");
+        };
+        rendered.push_str(value);
+
+        rest = &rest[start + len + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Sorts `day2` before `day10`
+fn module_key(module: &str) -> (&str, Option<u64>) {
+    let prefix = module.trim_end_matches(|c: char| c.is_ascii_digit());
+    (prefix, module[prefix.len()..].parse().ok())
+}
+
+/// Module declared by `line`
+fn declared(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("pub mod ")
+        .and_then(|l| l.strip_suffix(';'))
+        .map(str::trim)
+}
+
+/// `content` with `pub mod {module};` inserted in the sorted module declarations,
+/// or `None` if it is already declared. Every other line is left in place.
+fn insert_module(content: &str, module: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.iter().any(|&line| declared(line) == Some(module)) {
+        return None;
+    }
+
+    // After the last module sorting before it, so comments above the next one stay there
+    let position = lines
+        .iter()
+        .rposition(|&line| declared(line).is_some_and(|m| module_key(m) < module_key(module)))
+        .map(|previous| previous + 1)
+        .or_else(|| lines.iter().position(|&line| declared(line).is_some()))
+        .unwrap_or(lines.len());
+
+    let declaration = format!("pub mod {module};");
+    let mut new_content = String::with_capacity(content.len() + declaration.len() + 1);
+    for (i, line) in lines.iter().enumerate() {
+        if i == position {
+            new_content.push_str(&declaration);
+            new_content.push('\n');
+        }
+        new_content.push_str(line);
+        new_content.push('\n');
+    }
+    if position == lines.len() {
+        new_content.push_str(&declaration);
+        new_content.push('\n');
+    }
+
+    Some(new_content)
+}
+
+/// Adds `pub mod {module};` to the module list, keeping declarations sorted
+fn add_module(mod_file: &Path, module: &str) -> color_eyre::Result<()> {
+    let content = std::fs::read_to_string(mod_file)?;
+
+    if let Some(new_content) = insert_module(&content, module) {
+        std::fs::write(mod_file, new_content)?;
+    }
+
+    Ok(())
+}
+
+pub fn init(workspace: &Path, year: i32, day: u32, force: bool) -> color_eyre::Result<()> {
+    let module = format!("day{day}");
+    let templates = workspace.join("templates");
+    let problems = workspace.join("problems");
+
+    let generated: [(PathBuf, PathBuf); 2] = [
+        (
+            templates.join("solution.rs"),
+            problems.join(format!("src/solutions/{module}.rs")),
+        ),
+        (
+            templates.join("bin.rs"),
+            problems.join(format!("src/bin/{module}.rs")),
+        ),
+    ];
+
+    let existing: Vec<_> = generated
+        .iter()
+        .filter(|(_, target)| target.exists())
+        .map(|(_, target)| target.display().to_string())
+        .collect();
+    if !existing.is_empty() && !force {
+        eyre::bail!(
+            "Day {day} is already initialized ({}), use --force to overwrite",
+            existing.join(", ")
+        );
+    }
+
+    let variables = [
+        ("day", day.to_string()),
+        ("module", module.clone()),
+        ("year", year.to_string()),
+    ];
+
+    for (template, target) in &generated {
+        let template_content = std::fs::read_to_string(template)
+            .with_context(|| format!("Could not read template {template:?}"))?;
+        let content = render(&template_content, &variables)
+            .with_context(|| format!("Could not render template {template:?}"))?;
+        std::fs::write(target, content)?;
+    }
+
+    add_module(&problems.join("src/solutions/mod.rs"), &module)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{add_module, insert_module, render};
+
+    #[test]
+    fn modules() {
+        let content = "//! Solutions\n\npub mod day1;\npub mod day2;\n// Slow\npub mod day10;\n\nuse std::fmt;\n";
+
+        let added = insert_module(content, "day3").unwrap();
+        assert_eq!(
+            added,
+            "//! Solutions\n\npub mod day1;\npub mod day2;\npub mod day3;\n// Slow\npub mod day10;\n\nuse std::fmt;\n"
+        );
+        // Adding a module twice leaves the file as it was
+        assert_eq!(insert_module(&added, "day3"), None);
+        assert_eq!(insert_module(content, "day10"), None);
+
+        assert_eq!(
+            insert_module(content, "day11").unwrap(),
+            "//! Solutions\n\npub mod day1;\npub mod day2;\n// Slow\npub mod day10;\npub mod day11;\n\nuse std::fmt;\n"
+        );
+        assert_eq!(insert_module("", "day1").unwrap(), "pub mod day1;\n");
+        assert_eq!(
+            insert_module("//! Solutions\npub mod day2;\n", "day1").unwrap(),
+            "//! Solutions\npub mod day1;\npub mod day2;\n"
+        );
+    }
+
+    #[test]
+    fn add_twice() {
+        let mod_file = std::env::temp_dir().join(format!("aoc-init-{}.rs", std::process::id()));
+        std::fs::write(&mod_file, "pub mod day1;\n\n// Unsolved\npub mod day12;\n").unwrap();
+
+        add_module(&mod_file, "day2").unwrap();
+        let once = std::fs::read(&mod_file).unwrap();
+        add_module(&mod_file, "day2").unwrap();
+        let twice = std::fs::read(&mod_file).unwrap();
+        std::fs::remove_file(&mod_file).unwrap();
+
+        assert_eq!(
+            once,
+            b"pub mod day1;\npub mod day2;\n\n// Unsolved\npub mod day12;\n"
+        );
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn templates() {
+        let variables = [("day", "4".to_string()), ("module", "day4".to_string())];
+        assert_eq!(
+            render("{{module}}::main() // day {{ day }}, {{day}}", &variables).unwrap(),
+            "day4::main() // day 4, 4"
+        );
+        assert_eq!(render("no variables", &variables).unwrap(), "no variables");
+
+        assert_eq!(
+            render("{{ year }}", &variables).unwrap_err().to_string(),
+            "Unknown template variable 'year'"
+        );
+        assert_eq!(
+            render("{{ day", &variables).unwrap_err().to_string(),
+            "Unterminated template variable"
+        );
+    }
+}
@@ -1,60 +1,207 @@
 use std::{
-    fs::OpenOptions,
-    io::Write,
-    os::unix::process::CommandExt,
-    path::{Path, PathBuf},
+    fs::OpenOptions, io::Write, os::unix::process::CommandExt, path::PathBuf, time::Duration,
 };
 
-use chrono::{Datelike, Local};
-use clap::Parser;
-use color_eyre::eyre::{self, Context};
+use bench::BenchArgs;
+use chrono::{DateTime, Datelike, FixedOffset, TimeZone, Utc};
+use clap::{ArgAction, Parser, ValueEnum};
+use color_eyre::eyre;
+use config::{Config, ConfigFile};
+use profile::ProfileArgs;
 use reqwest::header::{self, HeaderValue};
 
+mod bench;
+mod config;
+mod init;
+mod profile;
+
 #[derive(Parser, Debug)]
 pub struct Args {
     #[arg(short, long)]
     day: Option<u32>,
     #[arg(short, long)]
-    part: Option<u32>,
+    part: Option<Part>,
     #[arg(short, long)]
     input: Option<PathBuf>,
     #[arg(short, long, env = "AOC_COOKIE")]
     cookie: Option<String>,
+    /// Year of the event, overriding the configuration
+    #[arg(long)]
+    year: Option<i32>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    release: Option<bool>,
+    /// Show the debug traces of the solution, twice to show every trace
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// Trace filter of the solution, overriding --verbose, for example `problems::solutions::day23=trace`
     #[arg(long)]
-    release: bool,
+    log: Option<String>,
+    /// Animate the simulation in the terminal, for the days supporting it
+    #[arg(long)]
+    visualize: bool,
     #[command(subcommand)]
     command: Option<Command>,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Part {
+    #[value(name = "1")]
+    One,
+    #[value(name = "2")]
+    Two,
+    Both,
+}
+
+impl Part {
+    fn name(self) -> &'static str {
+        match self {
+            Part::One => "1",
+            Part::Two => "2",
+            Part::Both => "both",
+        }
+    }
+}
+
 #[derive(Parser, Debug, Clone)]
 pub enum Command {
-    Init,
+    Init {
+        /// Overwrite the files of an already initialized day
+        #[arg(long)]
+        force: bool,
+    },
     Edit,
     Fetch,
     Run,
-    Bench { criterion_args: Vec<String> },
+    Bench {
+        #[command(flatten)]
+        bench: BenchArgs,
+        criterion_args: Vec<String>,
+    },
     Open,
-    Profile,
+    /// Profile the parsing and the parts selected with --part
+    Profile(ProfileArgs),
+    /// Wait for the puzzle to unlock, then init, fetch and open it
+    Wait,
+}
+
+/// Puzzles unlock at midnight US-Eastern, which is always EST (UTC-5) in December
+fn eastern() -> FixedOffset {
+    FixedOffset::west_opt(5 * 3600).unwrap()
+}
+
+fn unlock_time(year: i32, day: u32) -> color_eyre::Result<DateTime<FixedOffset>> {
+    if !(1..=25).contains(&day) {
+        eyre::bail!("Day {day} does not exist, days range from 1 to 25");
+    }
+
+    Ok(eastern()
+        .with_ymd_and_hms(year, 12, day, 0, 0, 0)
+        .single()
+        .expect("midnight EST is never ambiguous"))
 }
 
-fn fetch(day: u32, input_dir: &Path, cookie: &Option<String>) -> color_eyre::Result<()> {
-    let Some(cookie) = cookie else { eyre::bail!("Must provide cookie to fetch inputs") };
+fn format_remaining(remaining: chrono::Duration) -> String {
+    let secs = remaining.num_seconds().max(0);
+    let (days, secs) = (secs / 86400, secs % 86400);
+    let (hours, secs) = (secs / 3600, secs % 3600);
+    let (minutes, secs) = (secs / 60, secs % 60);
+
+    if days > 0 {
+        format!("{days}d {hours:02}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{hours:02}:{minutes:02}:{secs:02}")
+    }
+}
+
+fn ensure_unlocked(year: i32, day: u32, now: DateTime<Utc>) -> color_eyre::Result<()> {
+    let unlock = unlock_time(year, day)?;
+
+    if now < unlock {
+        eyre::bail!(
+            "Day {day} is not unlocked yet, it unlocks in {} (at {unlock})",
+            format_remaining(unlock.signed_duration_since(now))
+        );
+    }
+
+    Ok(())
+}
+
+/// The current day of the event, using the unlock timezone instead of the local one
+fn current_day(year: i32, now: DateTime<Utc>) -> color_eyre::Result<u32> {
+    let now = now.with_timezone(&eastern());
+
+    if now.year() != year || now.month() != 12 || now.day() > 25 {
+        eyre::bail!("Advent of Code {year} is not running, a day must be provided with --day");
+    }
+
+    Ok(now.day())
+}
+
+/// The next day that has not been unlocked yet
+fn next_day(year: i32, now: DateTime<Utc>) -> color_eyre::Result<u32> {
+    for day in 1..=25 {
+        if unlock_time(year, day)? > now {
+            return Ok(day);
+        }
+    }
+
+    eyre::bail!("All days of Advent of Code {year} are already unlocked")
+}
+
+fn wait_unlock(year: i32, day: u32) -> color_eyre::Result<()> {
+    let unlock = unlock_time(year, day)?;
+
+    loop {
+        let remaining = unlock.signed_duration_since(Utc::now());
+        if remaining <= chrono::Duration::zero() {
+            break;
+        }
+
+        print!("\r==> Day {day} unlocks in {}", format_remaining(remaining));
+        std::io::stdout().flush()?;
+
+        let tick = remaining
+            .to_std()
+            .unwrap_or_default()
+            .min(Duration::from_secs(1));
+        std::thread::sleep(tick);
+    }
+
+    println!("\r==> Day {day} is unlocked{:20}", "");
+
+    Ok(())
+}
+
+fn session(args: &Args, config: &Config) -> color_eyre::Result<String> {
+    match (&args.cookie, &config.session) {
+        (Some(cookie), _) => Ok(cookie.clone()),
+        (None, Some(source)) => source.read(&config.workspace),
+        (None, None) => eyre::bail!("Must provide cookie to fetch inputs"),
+    }
+}
+
+fn fetch(config: &Config, day: u32, cookie: &str) -> color_eyre::Result<()> {
+    ensure_unlocked(config.year, day, Utc::now())?;
 
     let client = reqwest::blocking::Client::new();
     let data = client
-        .get(format!("https://adventofcode.com/2022/day/{day}/input"))
+        .get(format!(
+            "https://adventofcode.com/{}/day/{day}/input",
+            config.year
+        ))
         .header(
             header::COOKIE,
             HeaderValue::from_str(&format!("session={cookie}"))?,
         )
         .send()?
+        .error_for_status()?
         .bytes()?;
 
     let mut input_file = OpenOptions::new()
         .create(true)
         .truncate(true)
         .write(true)
-        .open(input_dir.join(format!("day{day}")))?;
+        .open(config.inputs.join(format!("day{day}")))?;
 
     input_file.write_all(&data)?;
 
@@ -65,196 +212,135 @@ fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
     let args = Args::parse();
-    let time = Local::now();
-    let day = args.day.unwrap_or_else(|| time.day());
+    let config = Config::load(ConfigFile {
+        year: args.year,
+        release: args.release,
+        ..Default::default()
+    })?;
+
+    if let Some(Command::Bench {
+        bench,
+        criterion_args,
+    }) = &args.command
+    {
+        if bench.all {
+            if args.input.is_some() {
+                eyre::bail!("--input can't be used with --all, the inputs of every day are used");
+            }
+            return bench::run(&config, None, bench, criterion_args);
+        }
+    }
 
-    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
-    let inputs_dir = workspace.join("inputs");
+    let day = match (args.day, &args.command) {
+        (Some(day), _) => day,
+        (None, Some(Command::Wait)) => next_day(config.year, Utc::now())?,
+        (None, _) => current_day(config.year, Utc::now())?,
+    };
+    unlock_time(config.year, day)?;
 
-    if !inputs_dir.exists() {
-        std::fs::create_dir(&inputs_dir)?;
+    let workspace = config.workspace.as_path();
+
+    if !config.inputs.exists() {
+        std::fs::create_dir_all(&config.inputs)?;
     }
 
     let day_str = format!("day{day}");
     let day_file = workspace.join(format!("problems/src/solutions/{day_str}.rs"));
-    let day_bin_file = workspace.join(format!("problems/src/bin/{day_str}.rs"));
-    let day_bench_file = workspace.join(format!("problems/benches/{day_str}.rs"));
 
-    let input = inputs_dir.join(&day_str);
+    let input = config.inputs.join(&day_str);
+    let puzzle_url = format!("https://adventofcode.com/{}/day/{day}", config.year);
 
-    match args.command {
+    match &args.command {
         Some(Command::Open) => {
-            open::that(format!("https://adventofcode.com/2022/day/{day}"))?;
+            ensure_unlocked(config.year, day, Utc::now())?;
+            open::that(puzzle_url)?;
         }
         Some(Command::Edit) => {
-            return Err(std::process::Command::new(std::env::var("EDITOR")?)
+            let Some(editor) = config.editor else {
+                eyre::bail!("No editor configured, set $EDITOR or viewers.editor")
+            };
+            return Err(std::process::Command::new(editor)
                 .arg(day_file)
                 .exec()
                 .into());
         }
-        Some(Command::Init) => {
-            let template = workspace.join("template.rs");
-            let day = workspace.join(day_file);
-            std::fs::copy(template, day)?;
-
-            let bin_content = format!(
-                r#"
-                    fn main() -> color_eyre::Result<()> {{
-                        problems::solutions::{day_str}::main()
-                    }}
-                "#
-            );
-
-            std::fs::write(day_bin_file, bin_content.as_bytes())?;
-
-            let mut solution_mod = OpenOptions::new()
-                .append(true)
-                .open(workspace.join("problems/src/solutions/mod.rs"))?;
-            writeln!(solution_mod, "pub mod {day_str};")?;
-
-            let bench_content = format!(
-                r#"
-                    use bstr::BString;
-                    use criterion::{{criterion_group, criterion_main, BenchmarkId, Criterion}};
-                    use problems::solutions::{day_str}::*;
-
-                    mod perf;
-
-                    fn day_bench(c: &mut Criterion) {{
-                        std::env::set_var("AOC_BENCH", "1");
-
-                        let input_path = std::env::var("AOC_INPUT").unwrap();
-                        let input: BString = std::fs::read(&input_path).unwrap().into();
-
-                        c.bench_with_input(BenchmarkId::new("parsing", &input_path), &input, |b, i| {{
-                            b.iter(|| parsing(i))
-                        }});
-
-                        let parsed = parsing(&input).unwrap();
-
-                        c.bench_with_input(BenchmarkId::new("part1", &input_path), &parsed, |b, i| {{
-                            b.iter(|| part1(i.clone()))
-                        }});
-                        c.bench_with_input(BenchmarkId::new("part2", &input_path), &parsed, |b, i| {{
-                            b.iter(|| part2(i.clone()))
-                        }});
-                    }}
+        &Some(Command::Init { force }) => init::init(workspace, config.year, day, force)?,
+        Some(Command::Fetch) => fetch(&config, day, &session(&args, &config)?)?,
+        Some(Command::Wait) => {
+            let cookie = session(&args, &config)?;
+            wait_unlock(config.year, day)?;
+
+            if !day_file.exists() {
+                init::init(workspace, config.year, day, false)?;
+            }
 
+            let mut attempt = 1;
+            while let Err(e) = fetch(&config, day, &cookie) {
+                if attempt == 5 {
+                    return Err(e.wrap_err("Could not fetch input after unlock"));
+                }
+                eprintln!("==> Fetching input failed ({e}), retrying");
+                std::thread::sleep(Duration::from_secs(2));
+                attempt += 1;
+            }
 
-                    criterion_group! {{
-                        name = benches;
-                        config = Criterion::default().with_profiler(perf::FlamegraphProfiler::new(100));
-                        targets = day_bench
-                    }}
-                    criterion_main!(benches);
-                "#
-            );
-            std::fs::write(day_bench_file, bench_content.as_bytes())?;
-
-            let mut problems_cargo_toml = OpenOptions::new()
-                .append(true)
-                .open(workspace.join("problems/Cargo.toml"))?;
-            writeln!(
-                problems_cargo_toml,
-                r#"
-                    [[bench]]
-                    name = "{day_str}"
-                    harness = false
-                "#
-            )?;
+            open::that(puzzle_url)?;
         }
-        Some(Command::Fetch) => fetch(day, &inputs_dir, &args.cookie)?,
-        Some(Command::Profile) => {
-            let input = args.input.unwrap_or(input);
+        Some(Command::Profile(profile)) => {
+            let input = args.input.clone().unwrap_or(input);
 
             if !input.exists() {
-                fetch(day, &inputs_dir, &args.cookie)?;
-            }
-
-            println!("==> Benching day {day}");
-            let mut command = std::process::Command::new(env!("CARGO"));
-            command
-                .env("AOC_INPUT", &input)
-                .current_dir(workspace.join("problems"))
-                .args(["criterion", "--bench"])
-                .arg(&day_str)
-                .args(["--", "--", "--profile-time=5"]);
-
-            if !command.spawn()?.wait()?.success() {
-                color_eyre::eyre::bail!("Criterion returned an error")
+                fetch(&config, day, &session(&args, &config)?)?;
             }
 
-            let criterion_profiles = workspace.join("target/criterion/profile");
-
-            let escaped_input = input
-                .to_str()
-                .ok_or_else(|| color_eyre::eyre::eyre!("Non utf-8 in path"))?
-                .replace('/', "_");
-
-            let open_part = |part| {
-                open::with(
-                    criterion_profiles
-                        .join(part)
-                        .join(&escaped_input)
-                        .join("flamegraph.svg"),
-                    "firefox",
-                )
-            };
-
-            open_part("parsing").context("could not open parsing flamegraph")?;
-            open_part("part1").context("could not open part1 flamegraph")?;
-            open_part("part2").context("could not open part2 flamegraph")?;
+            profile::run(&config, day, &input, args.part, profile)?;
         }
-        Some(Command::Bench { criterion_args }) => {
-            let input = args.input.unwrap_or(input);
+        Some(Command::Bench {
+            bench,
+            criterion_args,
+        }) => {
+            let input = args.input.clone().unwrap_or(input);
 
             if !input.exists() {
-                fetch(day, &inputs_dir, &args.cookie)?;
+                fetch(&config, day, &session(&args, &config)?)?;
             }
 
-            println!("==> Benching day {day}");
-            let mut command = std::process::Command::new(env!("CARGO"));
-            command
-                .env("AOC_INPUT", &input)
-                .current_dir(workspace.join("problems"))
-                .args(["criterion", "--bench"])
-                .arg(&day_str)
-                .args(criterion_args);
-
-            return Err(command.exec().into());
+            bench::run(&config, Some((day, &input)), bench, criterion_args)?;
         }
         Some(Command::Run) | None => {
-            let input = args.input.unwrap_or(input);
+            let input = args.input.clone().unwrap_or(input);
 
             if !input.exists() {
-                fetch(day, &inputs_dir, &args.cookie)?;
+                fetch(&config, day, &session(&args, &config)?)?;
             }
 
-            let day_impl = std::fs::read_to_string(day_file)?;
-            let impl_part = if day_impl.contains(r#"todo!("todo part2")"#) {
-                1
-            } else {
-                2
-            };
-
-            let part = args.part.unwrap_or(impl_part);
-
-            println!("==> Running day {day} part {}", part);
+            match args.part {
+                Some(part) => println!("==> Running day {day} part {}", part.name()),
+                None => println!("==> Running day {day}"),
+            }
             let mut command = std::process::Command::new(env!("CARGO"));
             command
                 .current_dir(workspace)
                 .args(["run", "--package", "problems", "--bin"])
                 .arg(&day_str);
 
-            if args.release {
+            if config.release {
                 command.arg("--release");
             }
 
-            command
-                .args(["--", "--part"])
-                .arg(part.to_string())
-                .arg("--input")
-                .arg(&input);
+            command.args(["--", "--input"]).arg(&input);
+            if let Some(part) = args.part {
+                command.arg("--part").arg(part.name());
+            }
+            if args.verbose > 0 {
+                command.arg(format!("-{}", "v".repeat(args.verbose.into())));
+            }
+            if let Some(filter) = &args.log {
+                command.arg("--log").arg(filter);
+            }
+            if args.visualize {
+                command.arg("--visualize");
+            }
 
             command.spawn()?.wait()?;
         }
@@ -262,3 +348,66 @@ fn main() -> color_eyre::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::{current_day, ensure_unlocked, format_remaining, next_day, unlock_time};
+
+    fn utc(month: u32, day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, month, day, hour, min, sec)
+            .unwrap()
+    }
+
+    #[test]
+    fn unlock() {
+        assert_eq!(unlock_time(2022, 5).unwrap(), utc(12, 5, 5, 0, 0));
+        for day in [0, 26] {
+            assert_eq!(
+                unlock_time(2022, day).unwrap_err().to_string(),
+                format!("Day {day} does not exist, days range from 1 to 25")
+            );
+        }
+
+        assert!(ensure_unlocked(2022, 5, utc(12, 5, 5, 0, 0)).is_ok());
+        assert_eq!(
+            ensure_unlocked(2022, 5, utc(12, 5, 4, 59, 59))
+                .unwrap_err()
+                .to_string(),
+            "Day 5 is not unlocked yet, it unlocks in 00:00:01 (at 2022-12-05 00:00:00 -05:00)"
+        );
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(current_day(2022, utc(12, 5, 4, 59, 59)).unwrap(), 4);
+        assert_eq!(current_day(2022, utc(12, 5, 5, 0, 0)).unwrap(), 5);
+        assert_eq!(current_day(2022, utc(12, 26, 4, 59, 59)).unwrap(), 25);
+        for now in [utc(12, 26, 5, 0, 0), utc(12, 1, 4, 59, 59)] {
+            assert_eq!(
+                current_day(2022, now).unwrap_err().to_string(),
+                "Advent of Code 2022 is not running, a day must be provided with --day"
+            );
+        }
+        assert!(current_day(2021, utc(12, 5, 5, 0, 0)).is_err());
+
+        assert_eq!(next_day(2022, utc(11, 30, 12, 0, 0)).unwrap(), 1);
+        assert_eq!(next_day(2022, utc(12, 5, 4, 59, 59)).unwrap(), 5);
+        assert_eq!(next_day(2022, utc(12, 5, 5, 0, 0)).unwrap(), 6);
+        assert!(next_day(2022, utc(12, 25, 5, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn remaining() {
+        assert_eq!(
+            format_remaining(Duration::seconds(3 * 3600 + 65)),
+            "03:01:05"
+        );
+        assert_eq!(
+            format_remaining(Duration::days(2) + Duration::seconds(59)),
+            "2d 00:00:59"
+        );
+        assert_eq!(format_remaining(Duration::seconds(-5)), "00:00:00");
+    }
+}
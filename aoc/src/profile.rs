@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, Context};
+use serde::Deserialize;
+
+use crate::{config::Config, Part};
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ProfileArgs {
+    /// Time spent profiling each benchmark, in seconds
+    #[arg(long, default_value_t = 5)]
+    pub profile_time: u64,
+    /// Only print the paths of the profiles instead of opening the flamegraphs
+    #[arg(long)]
+    pub no_open: bool,
+    /// Count the allocations of each phase instead of sampling the CPU
+    #[arg(long, conflicts_with = "counters")]
+    pub alloc: bool,
+    /// Read the hardware performance counters instead of sampling the CPU
+    #[arg(long)]
+    pub counters: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CounterStats {
+    Available {
+        cycles: u64,
+        instructions: u64,
+        cache_misses: u64,
+        branch_misses: u64,
+        instructions_per_cycle: Option<f64>,
+    },
+    Unavailable {
+        error: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+struct AllocStats {
+    allocations: u64,
+    bytes: u64,
+    peak_bytes: i64,
+}
+
+/// Benchmark groups to profile, parsing is only profiled when no part is selected
+fn groups(part: Option<Part>) -> &'static [&'static str] {
+    match part {
+        None => &["parsing", "part1", "part2"],
+        Some(Part::One) => &["part1"],
+        Some(Part::Two) => &["part2"],
+        Some(Part::Both) => &["part1", "part2"],
+    }
+}
+
+pub fn run(
+    config: &Config,
+    day: u32,
+    input: &Path,
+    part: Option<Part>,
+    args: &ProfileArgs,
+) -> color_eyre::Result<()> {
+    let groups = groups(part);
+
+    let manifest = config.workspace.join("target/aoc-profiles");
+    if manifest.exists() {
+        std::fs::remove_file(&manifest)?;
+    }
+
+    println!("==> Profiling day {day} ({})", groups.join(", "));
+    let mut command = std::process::Command::new(env!("CARGO"));
+    command
+        .env("AOC_DAY", day.to_string())
+        .env("AOC_INPUT", input)
+        .env("AOC_PARTS", groups.join(","))
+        .env("AOC_PROFILE_MANIFEST", &manifest)
+        .current_dir(config.workspace.join("problems"));
+
+    if args.alloc {
+        command
+            .env("AOC_ALLOC", "1")
+            .env(
+                "AOC_PROFILE_DIR",
+                config.workspace.join("target/criterion/profile"),
+            )
+            .args(["bench", "--bench", "days"]);
+    } else {
+        if args.counters {
+            command.env("AOC_PROFILER", "counters");
+        }
+        command
+            .args(["criterion", "--bench", "days"])
+            .args(["--", "--"])
+            .arg(format!("--profile-time={}", args.profile_time));
+    }
+
+    if !command.spawn()?.wait()?.success() {
+        eyre::bail!("Criterion returned an error")
+    }
+
+    let manifest = std::fs::read_to_string(&manifest)
+        .context("Criterion did not write any profile, check the benchmark output")?;
+    let profiles: Vec<(&str, PathBuf)> = manifest
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(id, dir)| (id, PathBuf::from(dir)))
+        .collect();
+
+    if args.alloc {
+        for (id, dir) in &profiles {
+            let report = dir.join("alloc.json");
+            let stats: AllocStats = serde_json::from_str(&std::fs::read_to_string(&report)?)
+                .with_context(|| format!("Invalid allocation report {report:?}"))?;
+            println!("==> {id}");
+            println!("  Allocations: {}", stats.allocations);
+            println!("  Allocated:   {} bytes", stats.bytes);
+            println!("  Peak heap:   {} bytes", stats.peak_bytes);
+            println!("  Report:      {}", report.display());
+        }
+
+        return Ok(());
+    }
+
+    if args.counters {
+        for (id, dir) in &profiles {
+            let report = dir.join("counters.json");
+            let stats: CounterStats = serde_json::from_str(&std::fs::read_to_string(&report)?)
+                .with_context(|| format!("Invalid counters report {report:?}"))?;
+            println!("==> {id}");
+            match stats {
+                CounterStats::Available {
+                    cycles,
+                    instructions,
+                    cache_misses,
+                    branch_misses,
+                    instructions_per_cycle,
+                } => {
+                    println!("  Cycles:        {cycles}");
+                    match instructions_per_cycle {
+                        Some(ipc) => println!("  Instructions:  {instructions} ({ipc:.2} IPC)"),
+                        None => println!("  Instructions:  {instructions}"),
+                    }
+                    println!("  Cache misses:  {cache_misses}");
+                    println!("  Branch misses: {branch_misses}");
+                }
+                CounterStats::Unavailable { error } => println!(
+                    "  Counters unavailable: {}",
+                    error.as_deref().unwrap_or("unknown error")
+                ),
+            }
+            println!("  Report:        {}", report.display());
+        }
+
+        return Ok(());
+    }
+
+    for (id, dir) in &profiles {
+        println!("==> {id}");
+        println!("  Flamegraph: {}", dir.join("flamegraph.svg").display());
+        println!("  Profile:    {}", dir.join("profile.pb").display());
+    }
+
+    if !args.no_open {
+        for (id, dir) in &profiles {
+            let flamegraph = dir.join("flamegraph.svg");
+            match &config.flamegraph_viewer {
+                Some(viewer) => open::with(flamegraph, viewer),
+                None => open::that(flamegraph),
+            }
+            .with_context(|| format!("could not open {id} flamegraph"))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::CounterStats;
+
+    #[test]
+    fn counters_without_cycles() {
+        let report = r#"{
+            "available": true,
+            "cycles": 0,
+            "instructions": 1200,
+            "cache_misses": 3,
+            "branch_misses": 4,
+            "instructions_per_cycle": null
+        }"#;
+        assert!(matches!(
+            serde_json::from_str(report).unwrap(),
+            CounterStats::Available {
+                instructions: 1200,
+                instructions_per_cycle: None,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use bstr::BString;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use problems::Implemented;
+
+mod perf;
+
+#[global_allocator]
+static ALLOCATOR: perf::CountingAllocator = perf::CountingAllocator;
+
+/// Input for `day`, or `None` if the day is filtered out by `AOC_DAY`.
+///
+/// The input is read from `AOC_INPUT` when benching the single day selected by
+/// `AOC_DAY`, and from `AOC_INPUTS` (by default the workspace `inputs`
+/// directory) otherwise.
+fn day_input(day: &str) -> Option<BString> {
+    if let Ok(filter) = std::env::var("AOC_DAY") {
+        if !filter
+            .split(',')
+            .any(|d| d.trim().trim_start_matches("day") == day.trim_start_matches("day"))
+        {
+            return None;
+        }
+    }
+
+    let input_path = match std::env::var_os("AOC_INPUT") {
+        Some(input) => {
+            let single_day = std::env::var("AOC_DAY").is_ok_and(|days| !days.contains(','));
+            assert!(
+                single_day,
+                "AOC_INPUT is the input of a single day, which must be selected with AOC_DAY"
+            );
+            PathBuf::from(input)
+        }
+        None => std::env::var_os("AOC_INPUTS")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../inputs"))
+            .join(day),
+    };
+
+    match std::fs::read(&input_path) {
+        Ok(input) => Some(input.into()),
+        Err(e) => {
+            eprintln!("Skipping {day}: could not read {input_path:?} ({e})");
+            None
+        }
+    }
+}
+
+/// Whether the `group` benchmarks are selected by `AOC_PARTS`
+fn bench_part(group: &str) -> bool {
+    match std::env::var("AOC_PARTS") {
+        Ok(parts) => parts.split(',').any(|part| part.trim() == group),
+        Err(_) => true,
+    }
+}
+
+/// Whether to count the allocations of each phase instead of benchmarking
+fn alloc_mode() -> bool {
+    std::env::var_os("AOC_ALLOC").is_some()
+}
+
+macro_rules! bench_days {
+    ($($day:ident),* $(,)?) => {
+        fn days_bench(c: &mut Criterion) {
+            problems::output::set_sink(problems::output::Sink::Discard);
+
+            $({
+                use problems::solutions::$day::*;
+
+                match day_input(stringify!($day)) {
+                    None => (),
+                    Some(_) if IMPLEMENTED == Implemented::None => (),
+                    Some(input) if alloc_mode() => {
+                        let (parsed, stats) = perf::count_allocations(|| parsing(&input).unwrap());
+                        if bench_part("parsing") {
+                            stats.write(concat!("parsing/", stringify!($day)));
+                        }
+                        // The input is cloned before counting so only the part's allocations are
+                        // counted, freeing the input inside the part does not add to them
+                        if bench_part("part1") {
+                            let input = parsed.clone();
+                            let (_, stats) = perf::count_allocations(move || part1(input));
+                            stats.write(concat!("part1/", stringify!($day)));
+                        }
+                        if IMPLEMENTED == Implemented::Both && bench_part("part2") {
+                            let input = parsed.clone();
+                            let (_, stats) = perf::count_allocations(move || part2(input));
+                            stats.write(concat!("part2/", stringify!($day)));
+                        }
+                    }
+                    Some(input) => {
+                        if bench_part("parsing") {
+                            c.benchmark_group("parsing").bench_with_input(
+                                stringify!($day),
+                                &input,
+                                |b, i| b.iter(|| parsing(i)),
+                            );
+                        }
+
+                        let parsed = parsing(&input).unwrap();
+
+                        if bench_part("clone") {
+                            c.benchmark_group("clone").bench_with_input(
+                                stringify!($day),
+                                &parsed,
+                                |b, i| b.iter(|| i.clone()),
+                            );
+                        }
+
+                        // The input is cloned in the setup so only the solve time is measured
+                        if bench_part("part1") {
+                            c.benchmark_group("part1")
+                                .bench_with_input(stringify!($day), &parsed, |b, i| {
+                                    b.iter_batched(|| i.clone(), part1, BatchSize::LargeInput)
+                                });
+                        }
+                        if IMPLEMENTED == Implemented::Both && bench_part("part2") {
+                            c.benchmark_group("part2")
+                                .bench_with_input(stringify!($day), &parsed, |b, i| {
+                                    b.iter_batched(|| i.clone(), part2, BatchSize::LargeInput)
+                                });
+                        }
+                    }
+                }
+            })*
+        }
+    };
+}
+
+include!(concat!(env!("OUT_DIR"), "/days.rs"));
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_profiler(perf::BenchProfiler::from_env());
+    targets = days_bench
+}
+criterion_main!(benches);
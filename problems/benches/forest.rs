@@ -0,0 +1,39 @@
+//! Scenic scores of day 8 on generated forests, larger than the puzzle input
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ndarray::Array2;
+use problems::solutions::day8::{scenic_scores, scenic_scores_naive};
+use rng::Rng;
+
+mod rng;
+
+/// A `size` × `size` forest shaped like a pyramid with some noise, like the
+/// puzzle inputs, the same for every run
+fn forest(size: usize) -> Array2<u8> {
+    let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+
+    let center = size as f64 / 2.;
+    Array2::from_shape_fn((size, size), |(y, x)| {
+        let distance = (x as f64 - center).abs().max((y as f64 - center).abs()) / center;
+        let height = (1. - distance) * 9.;
+        (height as u64 + rng.below(3)).saturating_sub(1).min(9) as u8
+    })
+}
+
+fn forest_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scenic_scores");
+    group.sample_size(10);
+
+    for size in [100, 250, 500] {
+        let forest = forest(size);
+        group.bench_with_input(BenchmarkId::new("naive", size), &forest, |b, f| {
+            b.iter(|| scenic_scores_naive(f))
+        });
+        group.bench_with_input(BenchmarkId::new("monotonic", size), &forest, |b, f| {
+            b.iter(|| scenic_scores(f))
+        });
+    }
+}
+
+criterion_group!(benches, forest_bench);
+criterion_main!(benches);
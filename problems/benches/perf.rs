@@ -1,7 +1,15 @@
-use std::{fs::File, os::raw::c_int};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs::{File, OpenOptions},
+    io::Write,
+    os::raw::c_int,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+};
 
 use criterion::profiler::Profiler;
-use pprof::ProfilerGuard;
+use perf_event::{events::Hardware, Builder, Counter, Group};
+use pprof::{protos::Message, ProfilerGuard};
 
 pub struct FlamegraphProfiler<'a> {
     frequency: c_int,
@@ -23,19 +31,259 @@ impl<'a> Profiler for FlamegraphProfiler<'a> {
         self.active_profiler = Some(ProfilerGuard::new(self.frequency).unwrap());
     }
 
-    fn stop_profiling(&mut self, _benchmark_id: &str, benchmark_dir: &std::path::Path) {
+    fn stop_profiling(&mut self, benchmark_id: &str, benchmark_dir: &std::path::Path) {
         std::fs::create_dir_all(benchmark_dir).unwrap();
         let flamegraph_path = benchmark_dir.join("flamegraph.svg");
         let flamegraph_file =
             File::create(&flamegraph_path).expect("Could not create flamegraph.svg");
 
         if let Some(profiler) = self.active_profiler.take() {
-            profiler
-                .report()
-                .build()
-                .unwrap()
+            let report = profiler.report().build().unwrap();
+            report
                 .flamegraph(flamegraph_file)
-                .expect("Could not write flamegraph")
+                .expect("Could not write flamegraph");
+
+            let mut profile = Vec::new();
+            report
+                .pprof()
+                .unwrap()
+                .encode(&mut profile)
+                .expect("Could not encode profile");
+            std::fs::write(benchmark_dir.join("profile.pb"), profile)
+                .expect("Could not write profile.pb");
+        }
+
+        record_profile(benchmark_id, benchmark_dir);
+    }
+}
+
+/// Hardware counters read around each profiled benchmark
+struct Counters {
+    group: Group,
+    cycles: Counter,
+    instructions: Counter,
+    cache_misses: Counter,
+    branch_misses: Counter,
+}
+
+impl Counters {
+    fn open() -> std::io::Result<Self> {
+        let mut group = Group::new()?;
+        let mut counter = |kind| Builder::new().group(&mut group).kind(kind).build();
+
+        let cycles = counter(Hardware::CPU_CYCLES)?;
+        let instructions = counter(Hardware::INSTRUCTIONS)?;
+        let cache_misses = counter(Hardware::CACHE_MISSES)?;
+        let branch_misses = counter(Hardware::BRANCH_MISSES)?;
+
+        Ok(Self {
+            group,
+            cycles,
+            instructions,
+            cache_misses,
+            branch_misses,
+        })
+    }
+
+    fn report(mut self) -> std::io::Result<serde_json::Value> {
+        self.group.disable()?;
+        let counts = self.group.read()?;
+
+        let cycles = counts[&self.cycles];
+        let instructions = counts[&self.instructions];
+
+        Ok(serde_json::json!({
+            "available": true,
+            "cycles": cycles,
+            "instructions": instructions,
+            "cache_misses": counts[&self.cache_misses],
+            "branch_misses": counts[&self.branch_misses],
+            // No cycles are counted in some VMs, or when the counters were multiplexed out
+            "instructions_per_cycle": (cycles != 0).then(|| instructions as f64 / cycles as f64),
+        }))
+    }
+}
+
+/// Profiler reading `perf_event_open` hardware counters, it writes an
+/// unavailable report instead of failing when the counters can't be opened
+#[derive(Default)]
+pub struct CountersProfiler {
+    active: Option<Counters>,
+    error: Option<String>,
+}
+
+impl Profiler for CountersProfiler {
+    fn start_profiling(&mut self, benchmark_id: &str, _benchmark_dir: &Path) {
+        let counters = Counters::open().and_then(|mut counters| {
+            counters.group.enable()?;
+            Ok(counters)
+        });
+
+        match counters {
+            Ok(counters) => self.active = Some(counters),
+            Err(e) => {
+                eprintln!("Hardware counters are unavailable for {benchmark_id}: {e}");
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn stop_profiling(&mut self, benchmark_id: &str, benchmark_dir: &Path) {
+        let report = match self.active.take().map(Counters::report) {
+            Some(Ok(report)) => report,
+            Some(Err(e)) => serde_json::json!({ "available": false, "error": e.to_string() }),
+            None => serde_json::json!({ "available": false, "error": self.error.take() }),
+        };
+
+        std::fs::create_dir_all(benchmark_dir).unwrap();
+        std::fs::write(
+            benchmark_dir.join("counters.json"),
+            serde_json::to_string_pretty(&report).unwrap(),
+        )
+        .expect("Could not write counters.json");
+
+        record_profile(benchmark_id, benchmark_dir);
+    }
+}
+
+/// The profiler selected by `AOC_PROFILER`, sampling flamegraphs by default
+pub enum BenchProfiler {
+    Flamegraph(FlamegraphProfiler<'static>),
+    Counters(CountersProfiler),
+}
+
+impl BenchProfiler {
+    pub fn from_env() -> Self {
+        match std::env::var("AOC_PROFILER").as_deref() {
+            Ok("counters") => BenchProfiler::Counters(CountersProfiler::default()),
+            _ => BenchProfiler::Flamegraph(FlamegraphProfiler::new(100)),
+        }
+    }
+}
+
+impl Profiler for BenchProfiler {
+    fn start_profiling(&mut self, benchmark_id: &str, benchmark_dir: &Path) {
+        match self {
+            BenchProfiler::Flamegraph(p) => p.start_profiling(benchmark_id, benchmark_dir),
+            BenchProfiler::Counters(p) => p.start_profiling(benchmark_id, benchmark_dir),
         }
     }
+
+    fn stop_profiling(&mut self, benchmark_id: &str, benchmark_dir: &Path) {
+        match self {
+            BenchProfiler::Flamegraph(p) => p.stop_profiling(benchmark_id, benchmark_dir),
+            BenchProfiler::Counters(p) => p.stop_profiling(benchmark_id, benchmark_dir),
+        }
+    }
+}
+
+/// Tells the runner where the profile of `benchmark_id` was written, so it
+/// does not need to guess how criterion lays out its directories
+pub fn record_profile(benchmark_id: &str, dir: &Path) {
+    if let Some(manifest) = std::env::var_os("AOC_PROFILE_MANIFEST") {
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(manifest)
+            .expect("Could not open profile manifest");
+        writeln!(manifest, "{benchmark_id}\t{}", dir.display())
+            .expect("Could not write profile manifest");
+    }
+}
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static CURRENT_BYTES: AtomicI64 = AtomicI64::new(0);
+static PEAK_BYTES: AtomicI64 = AtomicI64::new(0);
+
+/// System allocator that counts allocations while [`count_allocations`] runs
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn allocated(&self, size: usize) {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+            self.resized(size as i64);
+        }
+    }
+
+    fn resized(&self, delta: i64) {
+        if COUNTING.load(Ordering::Relaxed) {
+            let current = CURRENT_BYTES.fetch_add(delta, Ordering::Relaxed) + delta;
+            PEAK_BYTES.fetch_max(current, Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocated(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.allocated(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.resized(-(layout.size() as i64));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.allocated(new_size);
+        self.resized(-(layout.size() as i64));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AllocStats {
+    pub allocations: u64,
+    pub bytes: u64,
+    /// Highest amount of memory held at once, above what was held at the start
+    pub peak_bytes: i64,
+}
+
+impl AllocStats {
+    pub fn write(&self, benchmark_id: &str) {
+        let profiles = std::env::var_os("AOC_PROFILE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/criterion/profile")
+            });
+        let dir = profiles.join(benchmark_id);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let report = format!(
+            "{{\"allocations\": {}, \"bytes\": {}, \"peak_bytes\": {}}}\n",
+            self.allocations, self.bytes, self.peak_bytes
+        );
+        std::fs::write(dir.join("alloc.json"), report).expect("Could not write alloc.json");
+
+        record_profile(benchmark_id, &dir);
+    }
+}
+
+/// Counts the allocations done by `f`, the global allocator must be a [`CountingAllocator`]
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    ALLOCATED_BYTES.store(0, Ordering::Relaxed);
+    CURRENT_BYTES.store(0, Ordering::Relaxed);
+    PEAK_BYTES.store(0, Ordering::Relaxed);
+
+    COUNTING.store(true, Ordering::SeqCst);
+    let result = f();
+    COUNTING.store(false, Ordering::SeqCst);
+
+    let stats = AllocStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+    };
+
+    (result, stats)
 }
@@ -0,0 +1,18 @@
+//! Random numbers for the generated inputs of the benchmarks
+
+/// xorshift64 generator, giving the same numbers for a seed on every run
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    /// A number in `0..max`
+    pub fn below(&mut self, max: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % max
+    }
+}
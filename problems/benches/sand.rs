@@ -0,0 +1,61 @@
+//! Falling sand of day 14 on generated caves, shaped like the puzzle inputs
+
+use bstr::BString;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use problems::solutions::day14::{parsing, sand_count, sand_count_naive};
+use rng::Rng;
+
+mod rng;
+
+/// Rock paths of `paths` random ledges and walls starting above `depth`, the same for
+/// every run
+fn cave(paths: usize, depth: u64) -> BString {
+    let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+
+    let mut input = String::new();
+    for _ in 0..paths {
+        let (mut x, mut y) = (460 + rng.below(80), 10 + rng.below(depth - 10));
+        input += &format!("{x},{y}");
+        for segment in 0..2 + rng.below(3) {
+            let len = 1 + rng.below(8);
+            if segment % 2 == 0 {
+                x = if rng.below(2) == 0 { x + len } else { x - len };
+            } else {
+                y += len;
+            }
+            input += &format!(" -> {x},{y}");
+        }
+        input += "\n";
+    }
+    input.into()
+}
+
+fn sand_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sand");
+    group.sample_size(10);
+
+    for depth in [60, 120, 170] {
+        let rocks = parsing(&cave(depth as usize, depth)).unwrap();
+        for (part, floor) in [("abyss", false), ("floor", true)] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{part}/hashmap"), depth),
+                &rocks,
+                |b, r| {
+                    b.iter_batched(
+                        || r.clone(),
+                        |r| sand_count_naive(r, floor),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("{part}/bitmap"), depth),
+                &rocks,
+                |b, r| b.iter(|| sand_count(r, floor)),
+            );
+        }
+    }
+}
+
+criterion_group!(benches, sand_bench);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+use std::{io::Write, path::Path};
+
+/// Generates the list of day modules for the `days` bench, so adding a day
+/// does not require touching the bench
+fn main() {
+    println!("cargo:rerun-if-changed=src/solutions");
+
+    let mut days: Vec<u32> = std::fs::read_dir("src/solutions")
+        .expect("could not read solutions")
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?
+                .strip_prefix("day")?
+                .strip_suffix(".rs")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    days.sort_unstable();
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let mut days_file = std::fs::File::create(Path::new(&out_dir).join("days.rs")).unwrap();
+    writeln!(days_file, "bench_days!(").unwrap();
+    for day in days {
+        writeln!(days_file, "    day{day},").unwrap();
+    }
+    writeln!(days_file, ");").unwrap();
+}
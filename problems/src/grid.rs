@@ -0,0 +1,124 @@
+//! Loading of rectangular character grids.
+//!
+//! Lines may end with `\n` or `\r\n`, the final newline is optional and
+//! trailing empty lines are ignored. Line and column numbers in the errors
+//! start at 1.
+
+use bstr::ByteSlice;
+use ndarray::Array2;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum GridError {
+    #[error("Grid is empty")]
+    Empty,
+    #[error("Line {line} has {len} cells, expected {expected} like the first line")]
+    Ragged {
+        line: usize,
+        len: usize,
+        expected: usize,
+    },
+    #[error("Invalid cell {cell:?} at line {line}, column {column}")]
+    InvalidCell {
+        line: usize,
+        column: usize,
+        cell: char,
+    },
+}
+
+/// Parses a grid indexed by `(row, column)`, `cell` returns `None` for invalid cells
+pub fn parse_grid<T>(
+    input: &[u8],
+    mut cell: impl FnMut(u8) -> Option<T>,
+) -> Result<Array2<T>, GridError> {
+    let mut lines: Vec<_> = input.lines().collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    let Some(expected) = lines.first().map(|line| line.len()) else {
+        return Err(GridError::Empty);
+    };
+    if expected == 0 {
+        return Err(GridError::Empty);
+    }
+
+    let mut cells = Vec::with_capacity(lines.len() * expected);
+    for (y, line) in lines.iter().enumerate() {
+        if line.len() != expected {
+            return Err(GridError::Ragged {
+                line: y + 1,
+                len: line.len(),
+                expected,
+            });
+        }
+
+        for (x, &c) in line.iter().enumerate() {
+            cells.push(cell(c).ok_or(GridError::InvalidCell {
+                line: y + 1,
+                column: x + 1,
+                cell: c.into(),
+            })?);
+        }
+    }
+
+    Ok(Array2::from_shape_vec((lines.len(), expected), cells).expect("grid shape is checked"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_grid, GridError};
+
+    fn digit(c: u8) -> Option<u8> {
+        c.is_ascii_digit().then(|| c - b'0')
+    }
+
+    #[test]
+    fn rectangular() {
+        let grid = parse_grid(b"123\n456\n", digit).unwrap();
+        assert_eq!(grid.dim(), (2, 3));
+        assert_eq!(grid[(1, 0)], 4);
+    }
+
+    #[test]
+    fn crlf_without_final_newline() {
+        let grid = parse_grid(b"12\r\n34\r\n56", digit).unwrap();
+        assert_eq!(grid.dim(), (3, 2));
+        assert_eq!(grid[(2, 1)], 6);
+    }
+
+    #[test]
+    fn trailing_empty_lines() {
+        let grid = parse_grid(b"12\n34\n\n\n", digit).unwrap();
+        assert_eq!(grid.dim(), (2, 2));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_grid(b"", digit), Err(GridError::Empty));
+        assert_eq!(parse_grid(b"\n\n", digit), Err(GridError::Empty));
+        assert_eq!(
+            parse_grid(b"123\n45\n678\n", digit),
+            Err(GridError::Ragged {
+                line: 2,
+                len: 2,
+                expected: 3
+            })
+        );
+        assert_eq!(
+            parse_grid(b"12\n\n34\n", digit),
+            Err(GridError::Ragged {
+                line: 2,
+                len: 0,
+                expected: 2
+            })
+        );
+        assert_eq!(
+            parse_grid(b"12\n3x\n", digit),
+            Err(GridError::InvalidCell {
+                line: 2,
+                column: 2,
+                cell: 'x'
+            })
+        );
+    }
+}
@@ -1,51 +1,222 @@
-use std::path::PathBuf;
+use std::{
+    convert::Infallible,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use bstr::BString;
-use clap::Parser;
+use clap::{ArgAction, Parser, ValueEnum};
+use tracing_subscriber::EnvFilter;
 
+pub mod grid;
+pub mod monotonic;
+pub mod ocr;
+pub mod output;
+pub mod render;
 pub mod solutions;
+pub mod visualize;
 
 #[derive(Parser)]
 struct Args {
     #[arg(short, long)]
-    part: u32,
+    part: Option<Part>,
     #[arg(short, long)]
     input: PathBuf,
+    /// Show the debug traces of the solutions, twice to show every trace
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// Trace filter, overriding --verbose, for example `problems::solutions::day23=trace`
+    #[arg(long, env = "AOC_LOG")]
+    log: Option<String>,
+    /// Animate the simulation in the terminal, for the days supporting it
+    #[arg(long)]
+    visualize: bool,
+    /// Initial speed of the animation, in frames per second
+    #[arg(long, default_value_t = 20.)]
+    fps: f64,
+    /// Export the frames of the simulation to a .gif or .svg animation
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Size in pixels of each cell of the exported animation
+    #[arg(long, default_value_t = 4)]
+    scale: u32,
+    /// Export a trace of the simulation to a .csv or .json file, for the days supporting it
+    #[arg(long)]
+    trace: Option<PathBuf>,
+    /// Number of rounds the monkeys play, for day 11
+    #[arg(long)]
+    rounds: Option<usize>,
+    /// Divisor of the worry levels after each inspection, for day 11
+    #[arg(long)]
+    relief: Option<u128>,
+}
+
+/// Parts a day has a solution for, declared by each day as `IMPLEMENTED`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Implemented {
+    /// The day was initialized but has no solution yet
+    None,
+    Part1,
+    Both,
+}
+
+impl Implemented {
+    /// The part to run, `part` if it is implemented or every implemented part
+    pub fn select(self, part: Option<Part>) -> color_eyre::Result<Part> {
+        match (self, part) {
+            (Implemented::None, _) => {
+                color_eyre::eyre::bail!("No part is implemented for this day")
+            }
+            (Implemented::Part1, Some(Part::Two | Part::Both)) => {
+                color_eyre::eyre::bail!("Part 2 is not implemented for this day")
+            }
+            (_, Some(part)) => Ok(part),
+            (Implemented::Part1, None) => Ok(Part::One),
+            (Implemented::Both, None) => Ok(Part::Both),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Part {
+    #[value(name = "1")]
+    One,
+    #[value(name = "2")]
+    Two,
+    Both,
 }
 
 #[derive(Debug)]
 pub struct Context {
-    pub part: u32,
+    pub part: Part,
     pub input: BString,
+    pub trace: Option<PathBuf>,
+    pub rounds: Option<usize>,
+    pub relief: Option<u128>,
 }
 
+/// Prints a result line to the current [`output`] sink
 #[macro_export]
 macro_rules! print_res {
+    () => {
+        $crate::output::write(format_args!("\n"))
+    };
     ($($tt:tt)*) => {
-        if (!std::env::var("AOC_BENCH").is_ok()) {
-            println!($($tt)*)
-        }
+        $crate::output::write(format_args!("{}\n", format_args!($($tt)*)))
     };
 }
 
+/// Prints part of a result to the current [`output`] sink
 #[macro_export]
 macro_rules! print_res_part {
     ($($tt:tt)*) => {
-        if (!std::env::var("AOC_BENCH").is_ok()) {
-            print!($($tt)*)
-        }
+        $crate::output::write(format_args!($($tt)*))
     };
 }
 
-pub fn load() -> color_eyre::Result<Context> {
+/// Logs the solution traces to stderr, so they don't mix with the results
+fn init_logging(args: &Args) -> color_eyre::Result<()> {
+    let filter = match (&args.log, args.verbose) {
+        (Some(filter), _) => EnvFilter::try_new(filter)?,
+        (None, 0) => EnvFilter::new("warn"),
+        (None, 1) => EnvFilter::new("warn,problems=debug"),
+        (None, _) => EnvFilter::new("warn,problems=trace"),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .init();
+
+    Ok(())
+}
+
+pub fn load(implemented: Implemented) -> color_eyre::Result<Context> {
     color_eyre::install()?;
 
     let args = Args::parse();
+    init_logging(&args)?;
+
+    let part = implemented.select(args.part)?;
+
+    if args.visualize {
+        visualize::enable(args.fps);
+    }
+    if let Some(path) = args.record {
+        render::Format::from_path(&path)?;
+        visualize::export(path, args.scale, args.fps);
+    }
 
     let input = std::fs::read(args.input)?.into();
 
     Ok(Context {
-        part: args.part,
+        part,
         input,
+        trace: args.trace,
+        rounds: args.rounds,
+        relief: args.relief,
     })
 }
+
+fn timed<E>(solve: impl FnOnce() -> Result<(), E>) -> Result<Duration, E> {
+    let start = Instant::now();
+    solve()?;
+    Ok(start.elapsed())
+}
+
+/// Runs the requested parts on an already parsed input and prints the timings.
+///
+/// When running both parts the input is cloned for part 1, outside of its timing.
+pub fn run_parts<P: Clone>(
+    part: Part,
+    parsing: Duration,
+    parsed: P,
+    part1: impl FnOnce(P),
+    part2: impl FnOnce(P),
+) {
+    let solved = try_run_parts::<_, Infallible>(
+        part,
+        parsing,
+        parsed,
+        |input| {
+            part1(input);
+            Ok(())
+        },
+        |input| {
+            part2(input);
+            Ok(())
+        },
+    );
+    solved.unwrap_or_else(|e| match e {})
+}
+
+/// Same as [`run_parts`] for parts that can fail, stopping at the first error
+pub fn try_run_parts<P: Clone, E>(
+    part: Part,
+    parsing: Duration,
+    parsed: P,
+    part1: impl FnOnce(P) -> Result<(), E>,
+    part2: impl FnOnce(P) -> Result<(), E>,
+) -> Result<(), E> {
+    let timings = match part {
+        Part::One => timed(|| part1(parsed)).map(|part1| vec![("Solving", part1)]),
+        Part::Two => timed(|| part2(parsed)).map(|part2| vec![("Solving", part2)]),
+        Part::Both => {
+            let input = parsed.clone();
+            timed(|| part1(input)).and_then(|part1| {
+                let part2 = timed(|| part2(parsed))?;
+                Ok(vec![("Part 1", part1), ("Part 2", part2)])
+            })
+        }
+    };
+    visualize::finish();
+    let timings = timings?;
+
+    println!("  Parsing: {}", humantime::format_duration(parsing));
+    for (name, elapsed) in timings {
+        println!("  {name}: {}", humantime::format_duration(elapsed));
+    }
+
+    Ok(())
+}
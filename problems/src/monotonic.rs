@@ -0,0 +1,54 @@
+//! Next greater element queries with a monotonic stack, in linear time.
+
+/// For each element, the index of the first following element `next` such
+/// that `blocks(current, next)`, or `None` if there is no such element.
+///
+/// `blocks` must be transitive for the result to be correct.
+pub fn next_greater_by<T: Copy>(
+    values: &[T],
+    mut blocks: impl FnMut(T, T) -> bool,
+) -> Vec<Option<usize>> {
+    let mut next = vec![None; values.len()];
+    // Indices of the candidates for the elements on the left, the top being the closest
+    let mut stack: Vec<usize> = Vec::with_capacity(values.len());
+
+    for (i, &value) in values.iter().enumerate().rev() {
+        while let Some(&top) = stack.last() {
+            if blocks(value, values[top]) {
+                break;
+            }
+            stack.pop();
+        }
+        next[i] = stack.last().copied();
+        stack.push(i);
+    }
+
+    next
+}
+
+/// For each element, the index of the first following element that is strictly greater
+pub fn next_greater<T: Copy + Ord>(values: &[T]) -> Vec<Option<usize>> {
+    next_greater_by(values, |current, next| next > current)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{next_greater, next_greater_by};
+
+    #[test]
+    fn greater() {
+        assert_eq!(
+            next_greater(&[2, 1, 3, 3, 0, 4]),
+            [Some(2), Some(2), Some(5), Some(5), Some(5), None]
+        );
+        assert_eq!(next_greater::<u8>(&[]), []);
+    }
+
+    #[test]
+    fn greater_or_equal() {
+        assert_eq!(
+            next_greater_by(&[3, 0, 3, 7, 3], |current, next| next >= current),
+            [Some(2), Some(2), Some(3), None, None]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Reading of the letters drawn by puzzles on a pixel screen.
+//!
+//! Two fonts are known: the small one is 6 pixels high with a letter every 5
+//! columns, the large one is 10 pixels high with a letter every 8 columns.
+//! Letters are read from the first column of the screen, crop the screen
+//! beforehand if the message is drawn elsewhere.
+
+use ndarray::Array2;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OcrError {
+    #[error("No font is {0} pixels high, expected 6 or 10")]
+    Height(usize),
+    #[error("Unknown letter at column {column}:\n{glyph}")]
+    UnknownGlyph { column: usize, glyph: String },
+}
+
+struct Font {
+    height: usize,
+    pitch: usize,
+    glyphs: &'static [(char, &'static [&'static str])],
+}
+
+const SMALL: Font = Font {
+    height: 6,
+    pitch: 5,
+    glyphs: &[
+        ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+        ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+        ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+        ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+        ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+        ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+        ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+        ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+        ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+        ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+        ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+        ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+        ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+        ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+        ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+        ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ],
+};
+
+const LARGE: Font = Font {
+    height: 10,
+    pitch: 8,
+    glyphs: &[
+        (
+            'A',
+            &[
+                "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'B',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+                "#....#", "#####.",
+            ],
+        ),
+        (
+            'C',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#....#", ".####.",
+            ],
+        ),
+        (
+            'E',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'F',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'G',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+                "#...##", ".###.#",
+            ],
+        ),
+        (
+            'H',
+            &[
+                "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'J',
+            &[
+                "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+                "#...#.", ".###..",
+            ],
+        ),
+        (
+            'K',
+            &[
+                "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+                "#...#.", "#....#",
+            ],
+        ),
+        (
+            'L',
+            &[
+                "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'N',
+            &[
+                "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+                "#...##", "#....#",
+            ],
+        ),
+        (
+            'P',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'R',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'X',
+            &[
+                "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'Z',
+            &[
+                "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+    ],
+};
+
+impl Font {
+    fn for_height(height: usize) -> Result<&'static Font, OcrError> {
+        [&SMALL, &LARGE]
+            .into_iter()
+            .find(|font| font.height == height)
+            .ok_or(OcrError::Height(height))
+    }
+
+    fn read(&self, screen: &Array2<bool>, column: usize) -> Option<char> {
+        let lit = |y: usize, x: usize| x < screen.ncols() && screen[(y, x)];
+
+        self.glyphs
+            .iter()
+            .find(|(_, rows)| {
+                rows.iter().enumerate().all(|(y, row)| {
+                    (0..self.pitch)
+                        .all(|x| lit(y, column + x) == (row.as_bytes().get(x) == Some(&b'#')))
+                })
+            })
+            .map(|&(c, _)| c)
+    }
+}
+
+/// Draws the pixels of `screen` with `#` and `.`, one line per row
+pub fn draw(screen: &Array2<bool>) -> String {
+    screen
+        .rows()
+        .into_iter()
+        .flat_map(|row| {
+            row.iter()
+                .map(|&lit| if lit { '#' } else { '.' })
+                .chain(std::iter::once('\n'))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Reads the letters of a screen indexed by `(row, column)`, the font is
+/// chosen by the height of the screen. Blank cells between letters are
+/// read as spaces, trailing ones are ignored.
+pub fn read(screen: &Array2<bool>) -> Result<String, OcrError> {
+    let font = Font::for_height(screen.nrows())?;
+
+    let mut text = String::new();
+    for column in (0..screen.ncols()).step_by(font.pitch) {
+        let end = (column + font.pitch).min(screen.ncols());
+        let cell = screen.slice(ndarray::s![.., column..end]);
+        if !cell.iter().any(|&lit| lit) {
+            text.push(' ');
+            continue;
+        }
+
+        match font.read(screen, column) {
+            Some(c) => text.push(c),
+            None => {
+                return Err(OcrError::UnknownGlyph {
+                    column,
+                    glyph: draw(&cell.to_owned()),
+                })
+            }
+        }
+    }
+
+    Ok(text.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read, OcrError};
+    use indoc::indoc;
+    use ndarray::Array2;
+
+    fn screen(drawing: &str) -> Array2<bool> {
+        crate::grid::parse_grid(drawing.as_bytes(), |c| match c {
+            b'#' => Some(true),
+            b'.' => Some(false),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn small_font() {
+        let alphabet = indoc! {"
+            .##..###...##..####.####..##..#..#..###...##.#..#.#.....##..###..###...###.#..#.#...#####.
+            #..#.#..#.#..#.#....#....#..#.#..#...#.....#.#.#..#....#..#.#..#.#..#.#....#..#.#...#...#.
+            #..#.###..#....###..###..#....####...#.....#.##...#....#..#.#..#.#..#.#....#..#..#.#...#..
+            ####.#..#.#....#....#....#.##.#..#...#.....#.#.#..#....#..#.###..###...##..#..#...#...#...
+            #..#.#..#.#..#.#....#....#..#.#..#...#..#..#.#.#..#....#..#.#....#.#.....#.#..#...#..#....
+            #..#.###...##..####.#.....###.#..#..###..##..#..#.####..##..#....#..#.###...##....#..####.
+        "};
+        assert_eq!(read(&screen(alphabet)), Ok("ABCEFGHIJKLOPRSUYZ".into()));
+    }
+
+    #[test]
+    fn large_font() {
+        let alphabet = indoc! {"
+            ..##....#####....####...######..######...####...#....#.....###..#....#..#.......#....#..#####...#####...#....#..######..
+            .#..#...#....#..#....#..#.......#.......#....#..#....#......#...#...#...#.......##...#..#....#..#....#..#....#.......#..
+            #....#..#....#..#.......#.......#.......#.......#....#......#...#..#....#.......##...#..#....#..#....#...#..#........#..
+            #....#..#....#..#.......#.......#.......#.......#....#......#...#.#.....#.......#.#..#..#....#..#....#...#..#.......#...
+            #....#..#####...#.......#####...#####...#.......######......#...##......#.......#.#..#..#####...#####.....##.......#....
+            ######..#....#..#.......#.......#.......#..###..#....#......#...##......#.......#..#.#..#.......#..#......##......#.....
+            #....#..#....#..#.......#.......#.......#....#..#....#......#...#.#.....#.......#..#.#..#.......#...#....#..#....#......
+            #....#..#....#..#.......#.......#.......#....#..#....#..#...#...#..#....#.......#...##..#.......#...#....#..#...#.......
+            #....#..#....#..#....#..#.......#.......#...##..#....#..#...#...#...#...#.......#...##..#.......#....#..#....#..#.......
+            #....#..#####....####...######..#........###.#..#....#...###....#....#..######..#....#..#.......#....#..#....#..######..
+        "};
+        assert_eq!(read(&screen(alphabet)), Ok("ABCEFGHJKLNPRXZ".into()));
+    }
+
+    #[test]
+    fn crt() {
+        let crt = indoc! {"
+            ###..#....####.#..#.####..##..####.#..#.
+            #..#.#....#....#..#.#....#..#....#.#..#.
+            #..#.#....###..####.###..#......#..#..#.
+            ###..#....#....#..#.#....#.##..#...#..#.
+            #.#..#....#....#..#.#....#..#.#....#..#.
+            #..#.####.####.#..#.#.....###.####..##..
+        "};
+        assert_eq!(read(&screen(crt)), Ok("RLEHFGZU".into()));
+    }
+
+    #[test]
+    fn spaces() {
+        let text = indoc! {"
+            #..#..###......#..#..##..
+            #..#...#.......#..#.#..#.
+            ####...#.......####.#..#.
+            #..#...#.......#..#.#..#.
+            #..#...#.......#..#.#..#.
+            #..#..###......#..#..##..
+        "};
+        assert_eq!(read(&screen(text)), Ok("HI HO".into()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            read(&Array2::from_elem((5, 40), false)),
+            Err(OcrError::Height(5))
+        );
+
+        let unknown = indoc! {"
+            .##..#..#.
+            #..#.#..#.
+            #..#.#..#.
+            #..#.#..#.
+            #.##.#..#.
+            .###..##..
+        "};
+        assert_eq!(
+            read(&screen(unknown)),
+            Err(OcrError::UnknownGlyph {
+                column: 0,
+                glyph: ".##..\n#..#.\n#..#.\n#..#.\n#.##.\n.###.\n".into()
+            })
+        );
+    }
+}
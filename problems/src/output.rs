@@ -0,0 +1,65 @@
+//! Destination of the results printed by the solutions.
+//!
+//! Results are written with [`print_res!`](crate::print_res) and
+//! [`print_res_part!`](crate::print_res_part) to the sink of the current
+//! thread, which is stdout unless replaced with [`set_sink`] or [`capture`].
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Write as _},
+    io::Write as _,
+};
+
+pub enum Sink {
+    Stdout,
+    /// Drop every result, used when benchmarking
+    Discard,
+    /// Append the results to a buffer
+    Collect(String),
+    /// Redirect the results to a writer
+    Writer(Box<dyn std::io::Write>),
+}
+
+thread_local! {
+    static SINK: RefCell<Sink> = const { RefCell::new(Sink::Stdout) };
+}
+
+/// Replaces the sink of the current thread, returning the previous one
+pub fn set_sink(sink: Sink) -> Sink {
+    SINK.with(|s| s.replace(sink))
+}
+
+/// Runs `f`, collecting everything it prints instead of writing it to the current sink
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, String) {
+    let previous = set_sink(Sink::Collect(String::new()));
+    let res = f();
+    match set_sink(previous) {
+        Sink::Collect(output) => (res, output),
+        _ => unreachable!("sink was replaced while capturing"),
+    }
+}
+
+/// Parses `input` and runs both parts on it, collecting what each of them prints
+#[cfg(test)]
+pub(crate) fn capture_parts<T: Clone>(
+    input: &str,
+    parsing: impl FnOnce(&bstr::BString) -> color_eyre::Result<T>,
+    part1: impl FnOnce(T),
+    part2: impl FnOnce(T),
+) -> (String, String) {
+    let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+    (
+        capture(|| part1(input.clone())).1,
+        capture(|| part2(input)).1,
+    )
+}
+
+#[doc(hidden)]
+pub fn write(args: fmt::Arguments) {
+    SINK.with(|sink| match &mut *sink.borrow_mut() {
+        Sink::Stdout => print!("{args}"),
+        Sink::Discard => (),
+        Sink::Collect(buffer) => buffer.write_fmt(args).unwrap(),
+        Sink::Writer(writer) => writer.write_fmt(args).expect("Could not write result"),
+    })
+}
@@ -0,0 +1,310 @@
+//! Offline rendering of recorded frames to animated images.
+//!
+//! Every character of a [`Frame`] is drawn as a `scale` × `scale` square, with
+//! the color given by the palette of the frame. All frames share the canvas
+//! fitted to the union of their bounds.
+
+use std::{fmt::Write as _, io::Write, path::Path};
+
+use color_eyre::eyre;
+
+use crate::visualize::Frame;
+
+pub type Rgb = [u8; 3];
+
+/// Colors of the characters of a frame, unknown characters use [`FOREGROUND`]
+pub type Palette = &'static [(char, Rgb)];
+
+pub const BACKGROUND: Rgb = [0x0f, 0x0f, 0x23];
+pub const FOREGROUND: Rgb = [0xcc, 0xcc, 0xcc];
+
+pub const DEFAULT_PALETTE: Palette = &[
+    (' ', BACKGROUND),
+    ('.', BACKGROUND),
+    ('#', FOREGROUND),
+    ('@', [0xff, 0xff, 0x66]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Gif,
+    Svg,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> color_eyre::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gif") => Ok(Format::Gif),
+            Some("svg") => Ok(Format::Svg),
+            _ => eyre::bail!("Can't guess the image format of {path:?}, use .gif or .svg"),
+        }
+    }
+}
+
+pub fn color(palette: Palette, c: char) -> Rgb {
+    palette
+        .iter()
+        .find(|&&(p, _)| p == c)
+        .map(|&(_, rgb)| rgb)
+        .unwrap_or(FOREGROUND)
+}
+
+type Bounds = ((i64, i64), (i64, i64));
+
+fn union_bounds(frames: &[Frame]) -> Option<Bounds> {
+    frames
+        .iter()
+        .filter_map(Frame::bounds)
+        .reduce(|((ax, ay), (bx, by)), ((cx, cy), (dx, dy))| {
+            ((ax.min(cx), ay.min(cy)), (bx.max(dx), by.max(dy)))
+        })
+}
+
+/// Colors of each cell of `frame` inside `bounds`, row by row
+fn cell_colors(frame: &Frame, ((min_x, min_y), (max_x, max_y)): Bounds) -> Vec<Vec<Rgb>> {
+    (min_y..=max_y)
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| color(frame.palette(), frame.get(x, y)))
+                .collect()
+        })
+        .collect()
+}
+
+fn canvas_size(((min_x, min_y), (max_x, max_y)): Bounds, scale: u32) -> (u64, u64) {
+    let scale = scale as u64;
+    (
+        (max_x - min_x + 1) as u64 * scale,
+        (max_y - min_y + 1) as u64 * scale,
+    )
+}
+
+/// Encodes the frames as a looping GIF, showing each frame for `delay` hundredths of a second
+pub fn encode_gif(
+    frames: &[Frame],
+    scale: u32,
+    delay: u16,
+    out: impl Write,
+) -> color_eyre::Result<()> {
+    let Some(bounds) = union_bounds(frames) else {
+        eyre::bail!("No frames to render");
+    };
+    let (width, height) = canvas_size(bounds, scale);
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        eyre::bail!("Image of {width}x{height} is too large for a GIF, use a smaller scale");
+    };
+
+    let frames: Vec<_> = frames.iter().map(|f| cell_colors(f, bounds)).collect();
+
+    let mut palette: Vec<Rgb> = Vec::new();
+    for &color in frames.iter().flatten().flatten() {
+        if !palette.contains(&color) {
+            palette.push(color);
+        }
+    }
+    if palette.len() > 256 {
+        eyre::bail!("GIFs can only use 256 colors, frames use {}", palette.len());
+    }
+
+    let mut encoder = gif::Encoder::new(out, width, height, &palette.concat())?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    let scale = scale as usize;
+    for rows in frames {
+        let mut buffer = Vec::with_capacity(width as usize * height as usize);
+        for row in rows {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|color| {
+                    let index = palette.iter().position(|c| c == color).unwrap() as u8;
+                    std::iter::repeat_n(index, scale)
+                })
+                .collect();
+            for _ in 0..scale {
+                buffer.extend_from_slice(&line);
+            }
+        }
+
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, &buffer, None);
+        frame.delay = delay;
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(())
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Encodes the frames as a looping SVG animated with CSS, showing each frame
+/// for `delay` seconds
+pub fn encode_svg(frames: &[Frame], scale: u32, delay: f64) -> color_eyre::Result<String> {
+    let Some(bounds) = union_bounds(frames) else {
+        eyre::bail!("No frames to render");
+    };
+    let (width, height) = canvas_size(bounds, scale);
+    let background = color(frames[0].palette(), frames[0].background());
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" shape-rendering="crispEdges">"#
+    )?;
+    writeln!(
+        svg,
+        "<style>.f{{visibility:hidden;animation:show {:.3}s step-end infinite}}\
+         @keyframes show{{0%{{visibility:visible}}{:.3}%{{visibility:hidden}}}}</style>",
+        delay * frames.len() as f64,
+        100. / frames.len() as f64,
+    )?;
+    writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        hex(background)
+    )?;
+
+    for (i, frame) in frames.iter().enumerate() {
+        writeln!(
+            svg,
+            r#"<g class="f" style="animation-delay:{:.3}s">"#,
+            delay * i as f64
+        )?;
+        for (y, row) in cell_colors(frame, bounds).into_iter().enumerate() {
+            // Consecutive cells of the same color are drawn as a single rectangle
+            let mut x = 0;
+            for run in row.chunk_by(|a, b| a == b) {
+                if run[0] != background {
+                    writeln!(
+                        svg,
+                        r#"<rect x="{}" y="{}" width="{}" height="{scale}" fill="{}"/>"#,
+                        x * scale as usize,
+                        y * scale as usize,
+                        run.len() * scale as usize,
+                        hex(run[0])
+                    )?;
+                }
+                x += run.len();
+            }
+        }
+        writeln!(svg, "</g>")?;
+    }
+    writeln!(svg, "</svg>")?;
+
+    Ok(svg)
+}
+
+/// Writes the frames to `path`, in the format given by its extension
+pub fn export(path: &Path, frames: &[Frame], scale: u32, fps: f64) -> color_eyre::Result<()> {
+    match Format::from_path(path)? {
+        Format::Gif => {
+            let delay = (100. / fps).round().max(1.) as u16;
+            encode_gif(frames, scale, delay, std::fs::File::create(path)?)
+        }
+        Format::Svg => Ok(std::fs::write(path, encode_svg(frames, scale, 1. / fps)?)?),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use indoc::indoc;
+
+    use super::{encode_gif, encode_svg};
+    use crate::{output, solutions, visualize};
+
+    /// Compares `content` with the golden file `name`, set `AOC_BLESS` to update it
+    fn check_golden(name: &str, content: &[u8]) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("golden")
+            .join(name);
+
+        if std::env::var_os("AOC_BLESS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read(&path)
+            .unwrap_or_else(|e| panic!("Could not read {path:?} ({e}), run with AOC_BLESS=1"));
+        assert!(
+            expected == content,
+            "{name} differs from its golden file, run with AOC_BLESS=1 to update it"
+        );
+    }
+
+    fn check_frames(name: &str, frames: &[visualize::Frame]) {
+        let mut gif = Vec::new();
+        encode_gif(frames, 2, 10, &mut gif).unwrap();
+        check_golden(&format!("{name}.gif"), &gif);
+
+        let svg = encode_svg(frames, 2, 0.1).unwrap();
+        check_golden(&format!("{name}.svg"), svg.as_bytes());
+    }
+
+    #[test]
+    fn day14() {
+        use solutions::day14::{parsing, part1};
+
+        let input = indoc! {"
+            498,4 -> 498,6 -> 496,6
+            503,4 -> 502,4 -> 502,9 -> 494,9
+        "};
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        let ((_, frames), _) = output::capture(|| visualize::record(|| part1(input)));
+        assert_eq!(frames.len(), 24);
+
+        check_frames("day14", &frames);
+    }
+
+    #[test]
+    fn day17() {
+        use solutions::day17::{parsing, run_fall};
+
+        let input = parsing(&b">><<<>".to_vec().into()).unwrap();
+        let (height, frames) = visualize::record(|| run_fall(input, 3));
+        assert_eq!((height, frames.len()), (5, 14));
+        assert_eq!(frames.last().unwrap().bounds(), Some(((0, -10), (8, 0))));
+
+        check_frames("day17", &frames);
+    }
+
+    #[test]
+    fn day23() {
+        use solutions::day23::{parsing, part1};
+
+        let input = indoc! {"
+            .....
+            ..##.
+            ..#..
+            .....
+            ..##.
+            .....
+        "};
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        let ((_, frames), _) = output::capture(|| visualize::record(|| part1(input)));
+        assert_eq!(frames.len(), 10);
+
+        check_frames("day23", &frames);
+    }
+
+    #[test]
+    fn day24() {
+        use solutions::day24::{parsing, part1};
+
+        let input = indoc! {"
+            #.######
+            #>>.<^<#
+            #.<..<<#
+            #>v.><>#
+            #<^v^^>#
+            ######.#
+        "};
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        let ((_, frames), _) = output::capture(|| visualize::record(|| part1(input)));
+        assert_eq!(frames.len(), 19);
+
+        check_frames("day24", &frames);
+    }
+}
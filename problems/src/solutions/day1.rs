@@ -1,11 +1,13 @@
 use std::time::Instant;
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
 use itertools::Itertools;
 
 type Parsed = Vec<Vec<u64>>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     Ok(input
         .trim()
@@ -34,22 +36,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
-
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
@@ -1,102 +1,329 @@
-use std::time::Instant;
+use std::{collections::BTreeSet, fmt::Write, time::Instant};
 
-use crate::{load, print_res, print_res_part};
+use crate::{load, ocr, print_res, print_res_part, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
-use either::Either;
+use ndarray::Array2;
+use tracing::{enabled, trace, warn, Level};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instr {
     Noop,
-    AddxStart,
-    AddxDo(i64),
+    Addx(i64),
+}
+
+impl Instr {
+    /// Number of cycles the instruction takes to complete
+    pub fn cycles(self) -> u64 {
+        match self {
+            Instr::Noop => 1,
+            Instr::Addx(_) => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Instr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instr::Noop => write!(f, "noop"),
+            Instr::Addx(num) => write!(f, "addx {num}"),
+        }
+    }
 }
 
 type Parsed = Vec<Instr>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .lines()
-        .flat_map(|v| {
+        .map(|v| {
             if v == b"noop" {
-                Either::Left(std::iter::once(Ok(Instr::Noop)))
+                Ok(Instr::Noop)
             } else if let Some(num) = v.strip_prefix(b"addx") {
-                let num = match num.trim().to_str() {
-                    Ok(n) => n.parse().map_err(Into::into),
-                    Err(e) => Err(e.into()),
-                };
-                let instr = num.map(Instr::AddxDo);
-                Either::Right([Ok(Instr::AddxStart), instr].into_iter())
+                Ok(Instr::Addx(num.trim().to_str()?.parse()?))
             } else {
-                Either::Left(std::iter::once(Err(color_eyre::eyre::eyre!(
-                    "Invalid instruction: {}",
-                    v.as_bstr()
-                ))))
+                color_eyre::eyre::bail!("Invalid instruction: {}", v.as_bstr())
             }
         })
         .collect()
 }
 
-pub fn part1(input: Parsed) {
-    let mut acc = 1;
-    let mut signals = 0;
-    for (cycle, instr) in input.iter().enumerate().map(|(i, v)| (i + 1, v)).take(220) {
-        if cycle >= 20 && (cycle - 20) % 40 == 0 {
-            signals += acc * (cycle as i64);
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub x: i64,
+}
 
-        match instr {
-            Instr::Noop | Instr::AddxStart => {}
-            Instr::AddxDo(num) => {
-                acc += num;
-            }
-        }
+impl Default for Registers {
+    fn default() -> Self {
+        Self { x: 1 }
     }
+}
 
-    print_res!("Signal sum is: {signals}")
+/// Called during every cycle, numbered from 1, before the instruction
+/// completing in this cycle updates the registers
+pub trait Observer {
+    fn during(&mut self, cycle: u64, registers: &Registers);
 }
 
-pub fn part2(input: Parsed) {
-    let mut acc = 1;
-    for (cycle, instr) in input.iter().enumerate().take(40 * 6) {
-        let position = (cycle % 40) as i64;
+impl<F: FnMut(u64, &Registers)> Observer for F {
+    fn during(&mut self, cycle: u64, registers: &Registers) {
+        self(cycle, registers)
+    }
+}
 
-        if position == 0 {
-            print_res!()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub cycle: u64,
+    pub pc: usize,
+    pub instr: Instr,
+    pub registers: Registers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The run reached a breakpoint before this cycle
+    Breakpoint(u64),
+    /// The program is over
+    Halted,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cpu<'a> {
+    program: &'a [Instr],
+    pub registers: Registers,
+    pc: usize,
+    /// Cycles spent on the current instruction
+    progress: u64,
+    /// Number of completed cycles
+    cycle: u64,
+    breakpoints: BTreeSet<u64>,
+    paused: Option<u64>,
+    trace: Option<Vec<TraceEntry>>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Instr]) -> Self {
+        Self {
+            program,
+            registers: Registers::default(),
+            pc: 0,
+            progress: 0,
+            cycle: 0,
+            breakpoints: BTreeSet::new(),
+            paused: None,
+            trace: None,
         }
+    }
 
-        if position.abs_diff(acc) <= 1 {
-            print_res_part!("#");
-        } else {
-            print_res_part!(".");
+    /// Records the state of the CPU during each cycle
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    /// Stops [`Cpu::run`] before `cycle` starts
+    pub fn break_at(&mut self, cycle: u64) {
+        self.breakpoints.insert(cycle);
+    }
+
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// Runs a single cycle, returns `false` if the program is over
+    pub fn step(&mut self, observer: &mut impl Observer) -> bool {
+        let Some(&instr) = self.program.get(self.pc) else {
+            return false;
+        };
+
+        self.cycle += 1;
+        observer.during(self.cycle, &self.registers);
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry {
+                cycle: self.cycle,
+                pc: self.pc,
+                instr,
+                registers: self.registers,
+            });
         }
 
-        match instr {
-            Instr::Noop | Instr::AddxStart => {}
-            Instr::AddxDo(num) => {
-                acc += num;
+        self.progress += 1;
+        if self.progress == instr.cycles() {
+            match instr {
+                Instr::Noop => {}
+                Instr::Addx(num) => self.registers.x += num,
             }
+            self.pc += 1;
+            self.progress = 0;
         }
+
+        true
+    }
+
+    /// Runs until the next breakpoint or the end of the program
+    pub fn run(&mut self, observer: &mut impl Observer) -> Stop {
+        loop {
+            let next = self.cycle + 1;
+            if self.breakpoints.contains(&next) && self.paused != Some(next) {
+                self.paused = Some(next);
+                return Stop::Breakpoint(next);
+            }
+            if !self.step(observer) {
+                return Stop::Halted;
+            }
+        }
+    }
+
+    pub fn trace(&self) -> Option<&[TraceEntry]> {
+        self.trace.as_deref()
+    }
+
+    /// The recorded trace as a table, one line per cycle
+    pub fn dump_trace(&self) -> String {
+        let mut dump = String::from("cycle    pc      X  instruction\n");
+        for entry in self.trace().unwrap_or_default() {
+            writeln!(
+                dump,
+                "{:>5} {:>5} {:>6}  {}",
+                entry.cycle, entry.pc, entry.registers.x, entry.instr
+            )
+            .unwrap();
+        }
+        dump
+    }
+}
+
+/// Runs the program until `cycles` cycles are done, tracing them if enabled
+fn run_for(program: &[Instr], cycles: u64, observer: &mut impl Observer) {
+    let mut cpu = Cpu::new(program);
+    if enabled!(Level::TRACE) {
+        cpu = cpu.with_trace();
+    }
+
+    cpu.break_at(cycles + 1);
+    cpu.run(observer);
+    trace!("CPU trace:\n{}", cpu.dump_trace());
+}
+
+pub fn part1(input: Parsed) {
+    let mut signals = 0;
+    run_for(&input, 220, &mut |cycle, registers: &Registers| {
+        if cycle % 40 == 20 {
+            signals += registers.x * cycle as i64;
+        }
+    });
+
+    print_res!("Signal sum is: {signals}")
+}
+
+pub fn part2(input: Parsed) {
+    let mut screen = Array2::from_elem((6, 40), false);
+    run_for(&input, 40 * 6, &mut |cycle, registers: &Registers| {
+        let (row, column) = ((cycle - 1) as usize / 40, (cycle - 1) as usize % 40);
+        screen[(row, column)] = (column as i64).abs_diff(registers.x) <= 1;
+    });
+
+    print_res!();
+    print_res_part!("{}", ocr::draw(&screen));
+    match ocr::read(&screen) {
+        Ok(letters) => print_res!("Letters: {letters}"),
+        Err(e) => warn!("Could not read the screen: {e}"),
     }
-    print_res!()
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parsing, part1, part2, Cpu, Instr, Registers, Stop};
+    use crate::output::capture;
+    use indoc::indoc;
+
+    const SMALL: &str = indoc! {"
+        noop
+        addx 3
+        addx -5
+    "};
+
+    #[test]
+    fn cycles() {
+        let program = parsing(&SMALL.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(program, [Instr::Noop, Instr::Addx(3), Instr::Addx(-5)]);
+
+        let mut during = Vec::new();
+        let mut cpu = Cpu::new(&program);
+        assert_eq!(
+            cpu.run(&mut |cycle, registers: &Registers| during.push((cycle, registers.x))),
+            Stop::Halted
+        );
+        assert_eq!(during, [(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+        assert_eq!(cpu.registers.x, -1);
+        assert_eq!(cpu.cycle(), 5);
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn breakpoints() {
+        let program = parsing(&SMALL.as_bytes().to_vec().into()).unwrap();
+        let mut cpu = Cpu::new(&program);
+        cpu.break_at(1);
+        cpu.break_at(4);
 
-    Ok(())
+        let mut noop = |_, _: &Registers| {};
+        assert_eq!(cpu.run(&mut noop), Stop::Breakpoint(1));
+        assert_eq!(cpu.cycle(), 0);
+        assert_eq!(cpu.run(&mut noop), Stop::Breakpoint(4));
+        // The first addx completed at the end of cycle 3
+        assert_eq!((cpu.cycle(), cpu.registers.x), (3, 4));
+        assert_eq!(cpu.run(&mut noop), Stop::Halted);
+        assert_eq!(cpu.run(&mut noop), Stop::Halted);
+    }
+
+    #[test]
+    fn trace() {
+        let program = parsing(&SMALL.as_bytes().to_vec().into()).unwrap();
+        let mut cpu = Cpu::new(&program).with_trace();
+        cpu.run(&mut |_, _: &Registers| {});
+        assert_eq!(
+            cpu.dump_trace(),
+            indoc! {"
+                cycle    pc      X  instruction
+                    1     0      1  noop
+                    2     1      1  addx 3
+                    3     1      1  addx 3
+                    4     2      4  addx -5
+                    5     2      4  addx -5
+            "}
+        );
+    }
+
+    #[test]
+    fn observers() {
+        // X stays at 1, so the sprite covers the first three pixels of each row
+        let program = vec![Instr::Noop; 240];
+        assert_eq!(capture(|| part1(program.clone())).1, "Signal sum is: 720\n");
+        assert_eq!(
+            capture(|| part2(program)).1,
+            format!(
+                "\n{}",
+                "###.....................................\n".repeat(6)
+            )
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let err = parsing(&b"noop\nmul 3\n".to_vec().into()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid instruction: mul 3");
+    }
 }
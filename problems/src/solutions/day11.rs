@@ -1,45 +1,223 @@
-use std::{cell::RefCell, collections::VecDeque, time::Instant};
+use std::time::Instant;
 
-use crate::{load, print_res};
+use crate::{load, print_res, try_run_parts, Implemented};
 use bstr::{BString, ByteSlice};
+use fnv::FnvHashMap;
 use itertools::Itertools;
+use tracing::{debug, enabled, Level};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
 
-#[derive(Debug, Clone)]
-enum WorryOperand {
-    Literal(u64),
+impl Op {
+    fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        }
+    }
+}
+
+/// Operation of a monkey, computing the new worry level from the `old` one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
     Old,
+    Literal(u128),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum EvalErrorKind {
+    #[error("Overflow")]
+    Overflow,
+    #[error("Negative result")]
+    Negative,
+    #[error("Division by zero")]
+    DivisionByZero,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} computing `{expr}` with old = {old}")]
+pub struct EvalError {
+    pub kind: EvalErrorKind,
+    pub expr: String,
+    pub old: u128,
 }
 
-impl WorryOperand {
-    fn from_bytes(b: &[u8]) -> color_eyre::Result<Self> {
-        match b.trim() {
-            b"old" => Ok(Self::Old),
-            lit => Ok(Self::Literal(lit.to_str()?.parse()?)),
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Literal(l) => write!(f, "{l}"),
+            Expr::Binary(op, a, b) => {
+                // Operands are parenthesized when they are operations themselves
+                let operand = |e: &Expr| match e {
+                    Expr::Binary(..) => format!("({e})"),
+                    e => e.to_string(),
+                };
+                write!(f, "{} {} {}", operand(a), op.symbol(), operand(b))
+            }
         }
     }
 }
 
-#[derive(Debug, Clone)]
-enum WorryUpdate {
-    Mult(WorryOperand, WorryOperand),
-    Sum(WorryOperand, WorryOperand),
+/// Recursive descent parser, `*` and `/` bind tighter than `+` and `-`
+struct ExprParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&mut self) -> Option<u8> {
+        while self
+            .input
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+        self.input.get(self.pos).copied()
+    }
+
+    fn unexpected(&mut self) -> color_eyre::Report {
+        match self.peek() {
+            Some(c) => color_eyre::eyre::eyre!(
+                "Unexpected {:?} at column {} of `{}`",
+                c as char,
+                self.pos + 1,
+                self.input.as_bstr()
+            ),
+            None => color_eyre::eyre::eyre!("Unexpected end of `{}`", self.input.as_bstr()),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[Op],
+        mut operand: impl FnMut(&mut Self) -> color_eyre::Result<Expr>,
+    ) -> color_eyre::Result<Expr> {
+        let mut expr = operand(self)?;
+        while let Some(&op) = ops.iter().find(|op| self.peek() == Some(op.symbol() as u8)) {
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(operand(self)?));
+        }
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> color_eyre::Result<Expr> {
+        self.binary(&[Op::Add, Op::Sub], Self::term)
+    }
+
+    fn term(&mut self) -> color_eyre::Result<Expr> {
+        self.binary(&[Op::Mul, Op::Div], Self::atom)
+    }
+
+    fn atom(&mut self) -> color_eyre::Result<Expr> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                if self.peek() != Some(b')') {
+                    return Err(self.unexpected());
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let len = self.input[self.pos..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let literal = self.input[self.pos..self.pos + len].to_str()?.parse()?;
+                self.pos += len;
+                Ok(Expr::Literal(literal))
+            }
+            _ if self.input[self.pos..].starts_with(b"old") => {
+                self.pos += 3;
+                Ok(Expr::Old)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(input: &[u8]) -> color_eyre::Result<Self> {
+        let mut parser = ExprParser { input, pos: 0 };
+        let expr = parser.expr()?;
+        if parser.peek().is_some() {
+            return Err(parser.unexpected());
+        }
+        Ok(expr)
+    }
+
+    /// True if the expression can be computed modulo any number. Subtractions are not,
+    /// as they could only wrap around the modulus instead of going negative.
+    fn is_modular(&self) -> bool {
+        match self {
+            Expr::Old | Expr::Literal(_) => true,
+            Expr::Binary(Op::Sub | Op::Div, _, _) => false,
+            Expr::Binary(_, a, b) => a.is_modular() && b.is_modular(),
+        }
+    }
+
+    fn compute(&self, old: u128, modulus: Option<u128>) -> Result<u128, EvalErrorKind> {
+        let reduce = |v: u128| match modulus {
+            // 128 bits divisions are much slower, and rarely needed
+            Some(m) => match (u64::try_from(v), u64::try_from(m)) {
+                (Ok(v), Ok(m)) => (v % m) as u128,
+                _ => v % m,
+            },
+            None => v,
+        };
+        match self {
+            Expr::Old => Ok(reduce(old)),
+            &Expr::Literal(l) => Ok(reduce(l)),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.compute(old, modulus)?, b.compute(old, modulus)?);
+                let value = match op {
+                    Op::Add => a.checked_add(b),
+                    Op::Sub => Some(a.checked_sub(b).ok_or(EvalErrorKind::Negative)?),
+                    Op::Mul => a.checked_mul(b),
+                    Op::Div => Some(a.checked_div(b).ok_or(EvalErrorKind::DivisionByZero)?),
+                };
+                value.map(reduce).ok_or(EvalErrorKind::Overflow)
+            }
+        }
+    }
+
+    /// Computes the new worry level, modulo `modulus` if the expression [is modular](Expr::is_modular)
+    pub fn eval(&self, old: u128, modulus: Option<u128>) -> Result<u128, EvalError> {
+        self.compute(old, modulus).map_err(|kind| EvalError {
+            kind,
+            expr: self.to_string(),
+            old,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Monkey {
-    items: VecDeque<u64>,
-    update: WorryUpdate,
-    test_diviser: u64,
+    items: Vec<u128>,
+    update: Expr,
+    test_diviser: u128,
     true_target: usize,
     false_target: usize,
-
-    inpsected: usize,
 }
 
 type Parsed = Vec<Monkey>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
-    input
+    let monkeys: Parsed = input
         .split_str("\n\n")
         .map(|monkey| -> color_eyre::Result<_> {
             let mut lines = monkey.lines();
@@ -67,22 +245,13 @@ pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
             let Some(update) = next_line()?.trim().strip_prefix(b"Operation: new = ") else {
                 color_eyre::eyre::bail!("Missing operation magic")
             };
-            let op = if update.contains(&b'*') { "*" } else { "+" };
-            let (op_a, op_b) = update.split_once_str(op).unwrap();
-
-            let op_a = WorryOperand::from_bytes(op_a)?;
-            let op_b = WorryOperand::from_bytes(op_b)?;
-
-            let update = match op {
-                "*" => WorryUpdate::Mult(op_a, op_b),
-                "+" => WorryUpdate::Sum(op_a, op_b),
-                _ => unreachable!(),
-            };
+            let update = Expr::parse(update)?;
 
             let Some(test_diviser) = next_line()?.trim().strip_prefix(b"Test: divisible by ") else {
                 color_eyre::eyre::bail!("Missing test magic")
             };
             let test_diviser = test_diviser.to_str()?.parse()?;
+            color_eyre::eyre::ensure!(test_diviser != 0, "Monkeys can't test divisibility by 0");
 
             let Some(true_target) = next_line()?.trim().strip_prefix(b"If true: throw to monkey ") else {
                 color_eyre::eyre::bail!("Missing true target magic")
@@ -100,118 +269,510 @@ pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
                 test_diviser,
                 true_target,
                 false_target,
-
-                inpsected: 0,
             })
         })
-        .collect()
-}
+        .collect::<color_eyre::Result<_>>()?;
 
-impl WorryOperand {
-    fn value(&self, old: u64) -> u64 {
-        match self {
-            &WorryOperand::Literal(l) => l,
-            WorryOperand::Old => old,
+    for (i, monkey) in monkeys.iter().enumerate() {
+        for target in [monkey.true_target, monkey.false_target] {
+            color_eyre::eyre::ensure!(
+                target < monkeys.len(),
+                "Monkey {i} throws to monkey {target}, but there are only {} monkeys",
+                monkeys.len()
+            );
         }
     }
+
+    Ok(monkeys)
 }
 
-impl WorryUpdate {
-    fn monkey_inspects(&self, old: u64) -> u64 {
-        match self {
-            WorryUpdate::Mult(a, b) => a.value(old) * b.value(old),
-            WorryUpdate::Sum(a, b) => a.value(old) + b.value(old),
-        }
-    }
+/// How the monkeys play
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub rounds: usize,
+    /// Worry levels are divided by this after each inspection
+    pub relief: u128,
 }
 
+pub const PART1: Config = Config {
+    rounds: 20,
+    relief: 3,
+};
+
+pub const PART2: Config = Config {
+    rounds: 10000,
+    relief: 1,
+};
+
 impl Monkey {
-    fn turn(&mut self, monkeys: &[RefCell<Monkey>]) {
-        while let Some(item) = self.items.pop_front() {
-            self.inpsected += 1;
-
-            let worried_item = self.update.monkey_inspects(item);
-            let worried_item = worried_item / 3;
-            let target = if worried_item % self.test_diviser == 0 {
-                self.true_target
-            } else {
-                self.false_target
-            };
-            monkeys[target].borrow_mut().items.push_back(worried_item);
+    /// Inspects an item and returns its new worry level and the monkey it is thrown to
+    fn inspect(
+        &self,
+        worry: u128,
+        config: &Config,
+        modulus: Option<u128>,
+    ) -> Result<(u128, usize), EvalError> {
+        let worry = self.update.eval(worry, modulus)? / config.relief;
+        let target = if worry.is_multiple_of(self.test_diviser) {
+            self.true_target
+        } else {
+            self.false_target
+        };
+        Ok((worry, target))
+    }
+}
+
+/// Path of an item through the monkeys, round after round.
+///
+/// Items are independent from each other, so each one is followed on its own
+/// until it comes back to a previous state, after which the route repeats.
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// Monkeys inspecting the item, round after round
+    inspectors: Vec<usize>,
+    /// Start of each simulated round in `inspectors`, followed by the end of the last one
+    rounds: Vec<usize>,
+    /// First round of the repeating rounds and their count, if the item went back to a
+    /// previous state
+    pub cycle: Option<(usize, usize)>,
+}
+
+impl Route {
+    /// Follows an item held by `holder`.
+    ///
+    /// A monkey only inspects the items it holds at the start of its turn, so items thrown
+    /// to a monkey that already played, or to itself, are inspected during the next round.
+    fn follow(
+        monkeys: &[Monkey],
+        mut holder: usize,
+        mut worry: u128,
+        config: &Config,
+        modulus: Option<u128>,
+    ) -> Result<Self, EvalError> {
+        let mut route = Route {
+            inspectors: Vec::new(),
+            rounds: vec![0],
+            cycle: None,
+        };
+        let mut seen = FnvHashMap::default();
+
+        for round in 0..config.rounds {
+            if let Some(start) = seen.insert((holder, worry), round) {
+                route.cycle = Some((start, round - start));
+                break;
+            }
+
+            loop {
+                route.inspectors.push(holder);
+                let (new_worry, target) = monkeys[holder].inspect(worry, config, modulus)?;
+                let next_round = target <= holder;
+                (holder, worry) = (target, new_worry);
+                if next_round {
+                    break;
+                }
+            }
+            route.rounds.push(route.inspectors.len());
         }
+
+        Ok(route)
     }
 
-    fn long_turn(&mut self, monkeys: &[RefCell<Monkey>], modulo_count: u64) {
-        while let Some(item) = self.items.pop_front() {
-            self.inpsected += 1;
+    /// Monkeys inspecting the item during `round`, numbered from 0
+    pub fn inspectors(&self, round: usize) -> &[usize] {
+        let round = match self.cycle {
+            Some((start, len)) if round >= start => start + (round - start) % len,
+            _ => round,
+        };
+        &self.inspectors[self.rounds[round]..self.rounds[round + 1]]
+    }
 
-            let worried_item = self.update.monkey_inspects(item);
-            let worried_item = worried_item % modulo_count;
-            let target = if worried_item % self.test_diviser == 0 {
-                self.true_target
-            } else {
-                self.false_target
-            };
-            monkeys[target].borrow_mut().items.push_back(worried_item);
+    /// Adds the inspections of the first `rounds` rounds to `counts`
+    fn count(&self, rounds: usize, counts: &mut [usize]) {
+        let simulated = self.rounds.len() - 1;
+        let (start, len) = self.cycle.unwrap_or((simulated, 1));
+
+        for round in 0..start.min(rounds) {
+            for &monkey in self.inspectors(round) {
+                counts[monkey] += 1;
+            }
+        }
+
+        // Each round of the cycle happens once per full cycle, and once more if it is in
+        // the last partial cycle
+        let repeated = rounds.saturating_sub(start);
+        for offset in 0..len.min(repeated) {
+            let times = repeated / len + usize::from(offset < repeated % len);
+            for &monkey in self.inspectors(start + offset) {
+                counts[monkey] += times;
+            }
         }
     }
 }
 
-pub fn part1(input: Parsed) {
-    let monkeys: Vec<_> = input.into_iter().map(RefCell::new).collect();
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// Route of every item, in the order of the starting items
+    pub routes: Vec<Route>,
+    pub monkeys: usize,
+    pub rounds: usize,
+}
 
-    for _ in 0..20 {
-        for monkey in &monkeys {
-            monkey.borrow_mut().turn(&monkeys);
+impl Simulation {
+    /// Number of items inspected by each monkey during `round`, numbered from 0
+    pub fn histogram(&self, round: usize) -> Vec<usize> {
+        assert!(
+            round < self.rounds,
+            "Round {round} was not played, only {} rounds were",
+            self.rounds
+        );
+
+        let mut counts = vec![0; self.monkeys];
+        for route in &self.routes {
+            for &monkey in route.inspectors(round) {
+                counts[monkey] += 1;
+            }
         }
+        counts
     }
-    let monkey_levels: usize = monkeys
-        .into_iter()
-        .map(|m| std::cmp::Reverse(m.into_inner().inpsected))
-        .k_smallest(2)
-        .map(|k| k.0)
-        .product();
 
-    print_res!("Monkey levels are: {monkey_levels}")
+    /// Number of items inspected by each monkey during the first `rounds` rounds
+    pub fn inspections(&self, rounds: usize) -> Vec<usize> {
+        assert!(
+            rounds <= self.rounds,
+            "Only {} rounds were played",
+            self.rounds
+        );
+
+        let mut counts = vec![0; self.monkeys];
+        for route in &self.routes {
+            route.count(rounds, &mut counts);
+        }
+        counts
+    }
 }
 
-pub fn part2(input: Parsed) {
-    let modulo_count: u64 = input.iter().map(|m| m.test_diviser).product();
+pub fn simulate(monkeys: &[Monkey], config: &Config) -> Result<Simulation, EvalError> {
+    assert!(config.relief != 0, "The relief divisor can't be 0");
 
-    let monkeys: Vec<_> = input.into_iter().map(RefCell::new).collect();
+    // Without relief worry levels only matter modulo the tests, as long as they are
+    // not divided
+    let modulus = (config.relief == 1 && monkeys.iter().all(|m| m.update.is_modular()))
+        .then(|| {
+            monkeys
+                .iter()
+                .try_fold(1u128, |acc, m| acc.checked_mul(m.test_diviser))
+        })
+        .flatten();
+
+    let routes = monkeys
+        .iter()
+        .enumerate()
+        .flat_map(|(i, monkey)| monkey.items.iter().map(move |&item| (i, item)))
+        .map(|(holder, worry)| Route::follow(monkeys, holder, worry, config, modulus))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Simulation {
+        routes,
+        monkeys: monkeys.len(),
+        rounds: config.rounds,
+    })
+}
 
-    for _ in 0..10000 {
-        for monkey in &monkeys {
-            monkey.borrow_mut().long_turn(&monkeys, modulo_count);
+/// Product of the inspection counts of the two most active monkeys
+pub fn monkey_business(input: Parsed, config: &Config) -> Result<usize, EvalError> {
+    let simulation = simulate(&input, config)?;
+
+    if enabled!(Level::DEBUG) {
+        for (i, route) in simulation.routes.iter().enumerate() {
+            if let Some((start, len)) = route.cycle {
+                debug!(
+                    "Item {i} repeats every {len} rounds from round {}",
+                    start + 1
+                );
+            }
+        }
+        for round in (1..=config.rounds).filter(|&r| r == 1 || r == 20 || r % 1000 == 0) {
+            debug!(
+                "Inspections after round {round}: {:?}",
+                simulation.inspections(round)
+            );
         }
     }
-    let monkey_levels: usize = monkeys
+
+    Ok(simulation
+        .inspections(config.rounds)
         .into_iter()
-        .map(|m| std::cmp::Reverse(m.into_inner().inpsected))
+        .map(std::cmp::Reverse)
         .k_smallest(2)
         .map(|k| k.0)
-        .product();
+        .product())
+}
+
+fn solve(input: Parsed, config: &Config) -> Result<(), EvalError> {
+    let monkey_levels = monkey_business(input, config)?;
+    print_res!("Monkey levels are: {monkey_levels}");
+    Ok(())
+}
+
+pub fn part1(input: Parsed) -> Result<(), EvalError> {
+    solve(input, &PART1)
+}
 
-    print_res!("Monkey levels are: {monkey_levels}")
+pub fn part2(input: Parsed) -> Result<(), EvalError> {
+    solve(input, &PART2)
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
+
+    color_eyre::eyre::ensure!(context.relief != Some(0), "The relief divisor can't be 0");
+    // The parts only differ by their rules, which can be overridden from the command line
+    let rules = |default: Config| Config {
+        rounds: context.rounds.unwrap_or(default.rounds),
+        relief: context.relief.unwrap_or(default.relief),
+    };
+    let (part1_rules, part2_rules) = (rules(PART1), rules(PART2));
+
+    try_run_parts(
+        context.part,
+        elapsed,
+        parsed,
+        |input| solve(input, &part1_rules),
+        |input| solve(input, &part2_rules),
+    )?;
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        monkey_business, parsing, part1, part2, simulate, Config, EvalErrorKind, Expr, PART1, PART2,
+    };
+    use crate::output::capture;
+    use indoc::indoc;
+
+    const EXAMPLE: &str = indoc! {"
+        Monkey 0:
+          Starting items: 79, 98
+          Operation: new = old * 19
+          Test: divisible by 23
+            If true: throw to monkey 2
+            If false: throw to monkey 3
+
+        Monkey 1:
+          Starting items: 54, 65, 75, 74
+          Operation: new = old + 6
+          Test: divisible by 19
+            If true: throw to monkey 2
+            If false: throw to monkey 0
+
+        Monkey 2:
+          Starting items: 79, 60, 97
+          Operation: new = old * old
+          Test: divisible by 13
+            If true: throw to monkey 1
+            If false: throw to monkey 3
+
+        Monkey 3:
+          Starting items: 74
+          Operation: new = old + 3
+          Test: divisible by 17
+            If true: throw to monkey 0
+            If false: throw to monkey 1
+    "};
+
+    #[test]
+    fn example() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(
+            capture(|| part1(input.clone()).unwrap()).1,
+            "Monkey levels are: 10605\n"
+        );
+        assert_eq!(
+            capture(|| part2(input).unwrap()).1,
+            "Monkey levels are: 2713310158\n"
+        );
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn expressions() {
+        let eval = |e: &str, old| Expr::parse(e.as_bytes()).unwrap().eval(old, None);
 
-    Ok(())
+        assert_eq!(eval("old * 19", 2), Ok(38));
+        assert_eq!(eval("old+old*2", 3), Ok(9));
+        assert_eq!(eval("(old + old) * 2", 3), Ok(12));
+        assert_eq!(eval("old - 4 - 3", 10), Ok(3));
+        assert_eq!(eval("old / 2 / 2", 10), Ok(2));
+        assert_eq!(eval("((old))", 7), Ok(7));
+
+        let expr = Expr::parse(b"(old - 1) * (old + 2) / 3").unwrap();
+        assert_eq!(expr.to_string(), "((old - 1) * (old + 2)) / 3");
+        assert_eq!(expr.eval(4, None), Ok(6));
+
+        assert!(Expr::parse(b"(old + 5) * old").unwrap().is_modular());
+        assert!(!Expr::parse(b"old - 5").unwrap().is_modular());
+        assert!(!Expr::parse(b"old / 5").unwrap().is_modular());
+    }
+
+    #[test]
+    fn expression_errors() {
+        let parse_err = |e: &str| Expr::parse(e.as_bytes()).unwrap_err().to_string();
+        assert_eq!(parse_err("old *"), "Unexpected end of `old *`");
+        assert_eq!(
+            parse_err("old % 2"),
+            "Unexpected '%' at column 5 of `old % 2`"
+        );
+        assert_eq!(parse_err("(old + 1"), "Unexpected end of `(old + 1`");
+
+        let eval_err = |e: &str, old| {
+            Expr::parse(e.as_bytes())
+                .unwrap()
+                .eval(old, None)
+                .unwrap_err()
+        };
+        assert_eq!(eval_err("old - 3", 2).kind, EvalErrorKind::Negative);
+        assert_eq!(
+            eval_err("old / (old - 2)", 2).kind,
+            EvalErrorKind::DivisionByZero
+        );
+        let overflow = eval_err("old * old", u128::MAX / 2);
+        assert_eq!(overflow.kind, EvalErrorKind::Overflow);
+        assert_eq!(
+            overflow.to_string(),
+            format!(
+                "Overflow computing `old * old` with old = {}",
+                u128::MAX / 2
+            )
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        // Dividing prevents reducing worry levels, which then overflow instead of wrapping
+        let input = EXAMPLE.replace("old * old", "old * old / 1");
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        let err = monkey_business(
+            input,
+            &Config {
+                rounds: 10000,
+                relief: 1,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, EvalErrorKind::Overflow);
+    }
+
+    #[test]
+    fn negative() {
+        let input = indoc! {"
+            Monkey 0:
+              Starting items: 7
+              Operation: new = old - 5
+              Test: divisible by 2
+                If true: throw to monkey 1
+                If false: throw to monkey 1
+
+            Monkey 1:
+              Starting items: 1
+              Operation: new = old * 1
+              Test: divisible by 3
+                If true: throw to monkey 0
+                If false: throw to monkey 0
+        "};
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+
+        // Subtracting prevents reducing worry levels, which then go negative instead of
+        // wrapping around the modulus
+        for config in [PART1, PART2] {
+            let err = monkey_business(input.clone(), &config).unwrap_err();
+            assert_eq!(err.kind, EvalErrorKind::Negative);
+        }
+
+        // The error is returned by the parts, without printing a result
+        let (err, printed) = capture(|| part1(input.clone()));
+        assert_eq!(err.unwrap_err().kind, EvalErrorKind::Negative);
+        assert_eq!(printed, "");
+        assert!(part2(input).is_err());
+    }
+
+    #[test]
+    fn inspections() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+
+        let simulation = simulate(&input, &PART1).unwrap();
+        assert_eq!(simulation.inspections(20), [101, 95, 7, 105]);
+
+        let simulation = simulate(&input, &PART2).unwrap();
+        assert_eq!(simulation.histogram(0), [2, 4, 3, 6]);
+        assert_eq!(simulation.inspections(1), [2, 4, 3, 6]);
+        assert_eq!(simulation.inspections(20), [99, 97, 8, 103]);
+        assert_eq!(simulation.inspections(1000), [5204, 4792, 199, 5192]);
+        assert_eq!(simulation.inspections(10000), [52166, 47830, 1938, 52013]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Round 20 was not played, only 20 rounds were")]
+    fn histogram_out_of_range() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+        simulate(&input, &PART1).unwrap().histogram(20);
+    }
+
+    #[test]
+    fn cycles() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+        let simulation = simulate(&input, &PART2).unwrap();
+
+        for route in &simulation.routes {
+            let (start, len) = route.cycle.expect("example items cycle");
+            for round in start..start + len {
+                assert_eq!(route.inspectors(round), route.inspectors(round + len));
+            }
+        }
+    }
+
+    #[test]
+    fn self_target() {
+        let input = indoc! {"
+            Monkey 0:
+              Starting items: 1, 2
+              Operation: new = old + 1
+              Test: divisible by 2
+                If true: throw to monkey 0
+                If false: throw to monkey 1
+
+            Monkey 1:
+              Starting items: 3
+              Operation: new = old * 2
+              Test: divisible by 3
+                If true: throw to monkey 1
+                If false: throw to monkey 0
+        "};
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        let config = Config {
+            rounds: 3,
+            relief: 1,
+        };
+        let simulation = simulate(&input, &config).unwrap();
+
+        // 1 -> 2 stays with monkey 0 until the next round, 2 -> 3 goes to monkey 1 which
+        // doubles it to 6 and keeps it for every following round
+        assert_eq!(simulation.histogram(0), [2, 2]);
+        assert_eq!(simulation.histogram(1), [1, 3]);
+        assert_eq!(simulation.inspections(3), [3, 8]);
+    }
+
+    #[test]
+    fn unknown_target() {
+        let input = EXAMPLE.replace("throw to monkey 3", "throw to monkey 4");
+        let err = parsing(&input.as_bytes().to_vec().into()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Monkey 0 throws to monkey 4, but there are only 4 monkeys"
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, time::Instant};
 
-use crate::{load, print_res};
-use bstr::{BString, ByteSlice};
+use crate::{grid::parse_grid, load, print_res, run_parts, Implemented};
+use bstr::BString;
 use petgraph::{graph::NodeIndex, Graph};
 
 #[derive(Clone, Debug)]
@@ -14,11 +14,13 @@ pub struct RiverMap {
 
 type Parsed = RiverMap;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
-    let Some(line_len) = input.find("\n") else {
-        color_eyre::eyre::bail!("Input has no lines")
-    };
-    let line_count = input.iter().filter(|&&x| x == b'\n').count();
+    let grid = parse_grid(input, |c| {
+        matches!(c, b'a'..=b'z' | b'S' | b'E').then_some(c)
+    })?;
+    let (line_count, line_len) = grid.dim();
 
     let mut nodes = HashMap::new();
     let mut graph = Graph::new();
@@ -32,7 +34,7 @@ pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     let mut start = None;
     let mut end = None;
 
-    let point_elevation = |x, y| input[x + y * line_len + y];
+    let point_elevation = |x, y| grid[(y, x)];
     let point_elevation_fixed = |x, y| match point_elevation(x, y) {
         b'S' => b'a',
         b'E' => b'z',
@@ -117,22 +119,56 @@ pub fn part2(mut input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parsing, part1, part2};
+    use crate::output::capture_parts;
+
+    const EXAMPLE: &str = "Sabqponm\nabcryxxl\naccszExk\nacctuvwj\nabdefghi\n";
+
+    #[test]
+    fn example() {
+        let expected = (
+            "Shortest path is of length: 31\n".to_string(),
+            "Shortest path from any is: 29\n".to_string(),
+        );
+
+        for input in [EXAMPLE, EXAMPLE.trim_end(), &EXAMPLE.replace('\n', "\r\n")] {
+            assert_eq!(capture_parts(input, parsing, part1, part2), expected);
+        }
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn single_row() {
+        assert_eq!(
+            capture_parts("SabcdefghijklmnopqrstuvwxyE", parsing, part1, part2),
+            (
+                "Shortest path is of length: 26\n".into(),
+                "Shortest path from any is: 25\n".into()
+            )
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn invalid() {
+        let err = parsing(&b"Sab\nab\nabE\n".to_vec().into()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 2 has 2 cells, expected 3 like the first line"
+        );
+
+        let err = parsing(&b"Sab\na#c\nabE\n".to_vec().into()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid cell '#' at line 2, column 2");
+    }
 }
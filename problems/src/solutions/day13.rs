@@ -1,71 +1,339 @@
-use std::time::Instant;
+use std::{cmp::Ordering, fmt::Write, time::Instant};
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
-use color_eyre::eyre::Context;
-use itertools::Itertools;
-use serde::Deserialize;
-
-#[derive(Clone, Deserialize, PartialEq, Eq)]
-#[serde(untagged)]
-pub enum Value {
-    Num(i64),
-    List(Vec<Value>),
+use tracing::{debug, enabled, Level};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum PacketErrorKind {
+    #[error("packets must be lists")]
+    NotAList,
+    #[error("unexpected {0:?}")]
+    Unexpected(char),
+    #[error("unexpected end of packet")]
+    End,
+    #[error("number is too large")]
+    Overflow,
 }
 
-impl std::fmt::Debug for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Num(arg0) => write!(f, "{arg0}"),
-            Self::List(arg0) => f.debug_list().entries(arg0).finish(),
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid packet at line {line}, column {column}: {kind}")]
+pub struct PacketError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: PacketErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Num(u32),
+}
+
+/// Tokens of a valid packet, where numbers can be wrapped in lists on the fly
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    /// Tokens to return before reading the packet, from last to first
+    pending: Vec<Token>,
+}
+
+impl Tokens<'_> {
+    /// Reads a number that was already read as an opening bracket as `[num]`
+    fn wrap(&mut self, num: u32) {
+        self.pending.extend([Token::Close, Token::Num(num)]);
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if let Some(token) = self.pending.pop() {
+            return Some(token);
         }
+
+        let start = self.bytes.iter().position(|&b| b != b',')?;
+        self.bytes = &self.bytes[start..];
+        let (token, len) = match self.bytes[0] {
+            b'[' => (Token::Open, 1),
+            b']' => (Token::Close, 1),
+            _ => {
+                let len = self.bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+                let num = self.bytes[..len]
+                    .iter()
+                    .fold(0, |n, d| n * 10 + (d - b'0') as u32);
+                (Token::Num(num), len)
+            }
+        };
+        self.bytes = &self.bytes[len..];
+        Some(token)
     }
 }
 
-macro_rules! v {
-    ($($items:expr)?) => {
-        Value::List(vec![$($items)*])
-    };
+/// A valid packet, borrowed from the input
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<'a>(&'a [u8]);
+
+impl<'a> Packet<'a> {
+    /// Checks that `bytes` is a list of numbers and lists, giving the offset of errors
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, (usize, PacketErrorKind)> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Expect {
+            ItemOrClose,
+            Item,
+            CommaOrClose,
+        }
+
+        let unexpected =
+            |i: usize| PacketErrorKind::Unexpected(bytes[i..].chars().next().unwrap_or('\u{fffd}'));
+
+        if bytes.first() != Some(&b'[') {
+            return Err((0, PacketErrorKind::NotAList));
+        }
+
+        let mut depth = 0;
+        let mut expect = Expect::Item;
+        let mut i = 0;
+        while i < bytes.len() {
+            match (bytes[i], expect) {
+                (b'[', Expect::Item | Expect::ItemOrClose) => {
+                    depth += 1;
+                    expect = Expect::ItemOrClose;
+                }
+                (b']', Expect::ItemOrClose | Expect::CommaOrClose) => {
+                    depth -= 1;
+                    expect = Expect::CommaOrClose;
+                    if depth == 0 {
+                        if i + 1 != bytes.len() {
+                            return Err((i + 1, unexpected(i + 1)));
+                        }
+                        return Ok(Packet(bytes));
+                    }
+                }
+                (b',', Expect::CommaOrClose) => expect = Expect::Item,
+                (b'0'..=b'9', Expect::Item | Expect::ItemOrClose) => {
+                    let len = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+                    bytes[i..i + len]
+                        .iter()
+                        .try_fold(0u32, |n, d| {
+                            n.checked_mul(10)?.checked_add((d - b'0') as u32)
+                        })
+                        .ok_or((i, PacketErrorKind::Overflow))?;
+                    expect = Expect::CommaOrClose;
+                    i += len;
+                    continue;
+                }
+                _ => return Err((i, unexpected(i))),
+            }
+            i += 1;
+        }
+
+        Err((bytes.len(), PacketErrorKind::End))
+    }
+
+    fn tokens(&self) -> Tokens<'a> {
+        Tokens {
+            bytes: self.0,
+            pending: Vec::new(),
+        }
+    }
+
+    fn to_value(self) -> Value {
+        let mut stack = vec![Vec::new()];
+        for token in self.tokens() {
+            match token {
+                Token::Open => stack.push(Vec::new()),
+                Token::Close => {
+                    let list = Value::List(stack.pop().unwrap());
+                    stack.last_mut().unwrap().push(list);
+                }
+                Token::Num(n) => stack.last_mut().unwrap().push(Value::Num(n)),
+            }
+        }
+        stack.pop().unwrap().pop().unwrap()
+    }
 }
 
-impl Ord for Value {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (self, other) {
-            (Value::Num(a), Value::Num(b)) => a.cmp(b),
-            (&Value::Num(x), b @ Value::List(_)) => Value::List(vec![Value::Num(x)]).cmp(b),
-            (a @ Value::List(_), &Value::Num(x)) => a.cmp(&Value::List(vec![Value::Num(x)])),
-            (Value::List(a), Value::List(b)) => a.cmp(b),
+impl std::fmt::Display for Packet<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.as_bstr())
+    }
+}
+
+/// Compares the token streams directly, wrapping numbers compared to lists
+impl Ord for Packet<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (mut left, mut right) = (self.tokens(), other.tokens());
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => return Ordering::Equal,
+                (Some(Token::Num(a)), Some(Token::Num(b))) if a != b => return a.cmp(&b),
+                (Some(Token::Num(_)), Some(Token::Num(_)))
+                | (Some(Token::Open), Some(Token::Open))
+                | (Some(Token::Close), Some(Token::Close)) => {}
+                (Some(Token::Close), _) => return Ordering::Less,
+                (_, Some(Token::Close)) => return Ordering::Greater,
+                (Some(Token::Num(a)), Some(Token::Open)) => left.wrap(a),
+                (Some(Token::Open), Some(Token::Num(b))) => right.wrap(b),
+                (None, Some(_)) | (Some(_), None) => {
+                    unreachable!("packets are balanced, so they end on the same closing bracket")
+                }
+            }
         }
     }
 }
 
-impl PartialOrd for Value {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl PartialOrd for Packet<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-type Parsed = Vec<(Value, Value)>;
+impl PartialEq for Packet<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
 
-pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
-    input
-        .split_str("\n\n")
-        .map(|packets| -> color_eyre::Result<_> {
-            let Some((first, second)) = packets.split_once_str("\n") else {
-                color_eyre::eyre::bail!("Invalid packet pair: {}", packets.as_bstr())
-            };
+impl Eq for Packet<'_> {}
 
-            Ok((
-                serde_json::from_slice(first)
-                    .with_context(|| format!("Invalid json list: {}", first.as_bstr()))?,
-                serde_json::from_slice(second)
-                    .with_context(|| format!("Invalid json list: {}", second.as_bstr()))?,
-            ))
-        })
-        .collect()
+/// Packet as a tree, only used to explain comparisons
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Num(u32),
+    List(Vec<Value>),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{n}"),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn explain_values(left: &Value, right: &Value, depth: usize, out: &mut String) -> Ordering {
+    let indent = "  ".repeat(depth);
+    writeln!(out, "{indent}- Compare {left} vs {right}").unwrap();
+    let indent = "  ".repeat(depth + 1);
+
+    let order = match (left, right) {
+        (Value::Num(a), Value::Num(b)) => a.cmp(b),
+        (&Value::Num(a), Value::List(_)) => {
+            writeln!(
+                out,
+                "{indent}- Mixed types; convert left to [{a}] and retry comparison"
+            )
+            .unwrap();
+            return explain_values(&Value::List(vec![Value::Num(a)]), right, depth + 1, out);
+        }
+        (Value::List(_), &Value::Num(b)) => {
+            writeln!(
+                out,
+                "{indent}- Mixed types; convert right to [{b}] and retry comparison"
+            )
+            .unwrap();
+            return explain_values(left, &Value::List(vec![Value::Num(b)]), depth + 1, out);
+        }
+        (Value::List(a), Value::List(b)) => {
+            for (a, b) in a.iter().zip(b) {
+                let order = explain_values(a, b, depth + 1, out);
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+
+            let order = a.len().cmp(&b.len());
+            match order {
+                Ordering::Less => writeln!(
+                    out,
+                    "{indent}- Left side ran out of items, so inputs are in the right order"
+                ),
+                Ordering::Greater => writeln!(
+                    out,
+                    "{indent}- Right side ran out of items, so inputs are not in the right order"
+                ),
+                Ordering::Equal => Ok(()),
+            }
+            .unwrap();
+            return order;
+        }
+    };
+
+    match order {
+        Ordering::Less => writeln!(
+            out,
+            "{indent}- Left side is smaller, so inputs are in the right order"
+        ),
+        Ordering::Greater => writeln!(
+            out,
+            "{indent}- Right side is smaller, so inputs are not in the right order"
+        ),
+        Ordering::Equal => Ok(()),
+    }
+    .unwrap();
+    order
+}
+
+/// Compares two packets step by step, with the same trace as the puzzle text
+pub fn explain(left: Packet, right: Packet) -> (Ordering, String) {
+    let mut explanation = String::new();
+    let order = explain_values(&left.to_value(), &right.to_value(), 0, &mut explanation);
+    (order, explanation)
 }
 
-pub fn part1(input: Parsed) {
+type Parsed<'a> = Vec<(Packet<'a>, Packet<'a>)>;
+
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
+pub fn parsing(input: &BString) -> color_eyre::Result<Parsed<'_>> {
+    let mut pairs = Vec::new();
+    let mut first = None;
+
+    for (i, line) in input.lines().enumerate() {
+        if line.is_empty() {
+            if first.is_some() {
+                color_eyre::eyre::bail!("Packet at line {i} has no pair");
+            }
+            continue;
+        }
+
+        let packet = Packet::parse(line).map_err(|(column, kind)| PacketError {
+            line: i + 1,
+            column: column + 1,
+            kind,
+        })?;
+        match first.take() {
+            None => first = Some(packet),
+            Some(first) => pairs.push((first, packet)),
+        }
+    }
+
+    if first.is_some() {
+        color_eyre::eyre::bail!("The last packet has no pair");
+    }
+
+    Ok(pairs)
+}
+
+pub fn part1(input: Parsed<'_>) {
+    if enabled!(Level::DEBUG) {
+        for (i, &(a, b)) in input.iter().enumerate() {
+            debug!("== Pair {} ==\n{}", i + 1, explain(a, b).1.trim_end());
+        }
+    }
+
     let index_sum: usize = input
         .iter()
         .enumerate()
@@ -75,37 +343,158 @@ pub fn part1(input: Parsed) {
     print_res!("Sum of in order indices is: {index_sum}");
 }
 
-pub fn part2(input: Parsed) {
-    let extra_values = [&v![v![Value::Num(2)]], &v![v![Value::Num(6)]]];
-    let decoder_key: usize = input
+pub fn part2(input: Parsed<'_>) {
+    // The position of a divider in the sorted packets is the number of packets before it
+    let dividers = [Packet(b"[[2]]"), Packet(b"[[6]]")];
+    let decoder_key: usize = dividers
         .iter()
-        .flat_map(|(a, b)| [a, b])
-        .chain(extra_values)
-        .sorted()
         .enumerate()
-        .filter(|(_, x)| extra_values.contains(x))
-        .map(|(i, _)| i + 1)
+        .map(|(i, divider)| {
+            input
+                .iter()
+                .flat_map(|(a, b)| [a, b])
+                .filter(|&packet| packet < divider)
+                .count()
+                + i
+                + 1
+        })
         .product();
     print_res!("Decoder key: {decoder_key}");
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use super::{explain, parsing, part1, part2, Packet, PacketErrorKind};
+    use crate::output::capture;
+    use indoc::indoc;
+
+    const EXAMPLE: &str = indoc! {"
+        [1,1,3,1,1]
+        [1,1,5,1,1]
+
+        [[1],[2,3,4]]
+        [[1],4]
+
+        [9]
+        [[8,7,6]]
+
+        [[4,4],4,4]
+        [[4,4],4,4,4]
+
+        [7,7,7,7]
+        [7,7,7]
+
+        []
+        [3]
+
+        [[[]]]
+        [[]]
+
+        [1,[2,[3,[4,[5,6,7]]]],8,9]
+        [1,[2,[3,[4,[5,6,0]]]],8,9]
+    "};
+
+    #[test]
+    fn example() {
+        let input = EXAMPLE.as_bytes().to_vec().into();
+        let input = parsing(&input).unwrap();
+        assert_eq!(
+            capture(|| part1(input.clone())).1,
+            "Sum of in order indices is: 13\n"
+        );
+        assert_eq!(capture(|| part2(input)).1, "Decoder key: 140\n");
+    }
+
+    #[test]
+    fn explain_matches_order() {
+        let input = EXAMPLE.as_bytes().to_vec().into();
+        let input = parsing(&input).unwrap();
+        for (a, b) in input {
+            assert_eq!(explain(a, b).0, a.cmp(&b), "{a} vs {b}");
+            assert_eq!(explain(b, a).0, b.cmp(&a), "{b} vs {a}");
+        }
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn explain_trace() {
+        let packet = |p: &'static str| Packet::parse(p.as_bytes()).unwrap();
 
-    Ok(())
+        assert_eq!(
+            explain(packet("[[1],[2,3,4]]"), packet("[[1],4]")),
+            (
+                Ordering::Less,
+                indoc! {"
+                    - Compare [[1],[2,3,4]] vs [[1],4]
+                      - Compare [1] vs [1]
+                        - Compare 1 vs 1
+                      - Compare [2,3,4] vs 4
+                        - Mixed types; convert right to [4] and retry comparison
+                        - Compare [2,3,4] vs [4]
+                          - Compare 2 vs 4
+                            - Left side is smaller, so inputs are in the right order
+                "}
+                .into()
+            )
+        );
+
+        assert_eq!(
+            explain(packet("[[[]]]"), packet("[[]]")),
+            (
+                Ordering::Greater,
+                indoc! {"
+                    - Compare [[[]]] vs [[]]
+                      - Compare [[]] vs []
+                        - Right side ran out of items, so inputs are not in the right order
+                "}
+                .into()
+            )
+        );
+    }
+
+    #[test]
+    fn wrapping() {
+        let packet = |p: &'static str| Packet::parse(p.as_bytes()).unwrap();
+        assert_eq!(packet("[[[1]]]"), packet("[1]"));
+        assert_eq!(packet("[[1],2]").cmp(&packet("[1,[2]]")), Ordering::Equal);
+        assert!(packet("[[1,2]]") > packet("[1]"));
+        assert!(packet("[10]") > packet("[9,9]"));
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |p: &str| Packet::parse(p.as_bytes()).unwrap_err();
+        assert_eq!(parse(""), (0, PacketErrorKind::NotAList));
+        assert_eq!(parse("3"), (0, PacketErrorKind::NotAList));
+        assert_eq!(parse("{}"), (0, PacketErrorKind::NotAList));
+        assert_eq!(parse("[1,2.5]"), (4, PacketErrorKind::Unexpected('.')));
+        assert_eq!(parse("[1,-2]"), (3, PacketErrorKind::Unexpected('-')));
+        assert_eq!(parse("[1,,2]"), (3, PacketErrorKind::Unexpected(',')));
+        assert_eq!(parse("[1,]"), (3, PacketErrorKind::Unexpected(']')));
+        assert_eq!(parse("[1 2]"), (2, PacketErrorKind::Unexpected(' ')));
+        assert_eq!(parse("[1]]"), (3, PacketErrorKind::Unexpected(']')));
+        assert_eq!(parse("[1,[2]"), (6, PacketErrorKind::End));
+        assert_eq!(parse("[99999999999]"), (1, PacketErrorKind::Overflow));
+
+        let err = parsing(&b"[1]\n[2,{}]\n".to_vec().into()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid packet at line 2, column 4: unexpected '{'"
+        );
+        let err = parsing(&b"[1]\n[2]\n\n[3]\n".to_vec().into()).unwrap_err();
+        assert_eq!(err.to_string(), "The last packet has no pair");
+    }
 }
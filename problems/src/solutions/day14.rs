@@ -1,6 +1,12 @@
 use std::{cmp::Ordering, collections::HashMap, time::Instant};
 
-use crate::{load, print_res};
+use crate::{
+    load, print_res,
+    render::{Palette, BACKGROUND},
+    run_parts,
+    visualize::{self, Frame},
+    Implemented,
+};
 use bstr::{BString, ByteSlice};
 use either::Either;
 use itertools::Itertools;
@@ -21,6 +27,8 @@ enum PairErr {
 
 type Parsed = HashMap<(u64, u64), Blocker>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 fn try_flat_map<U, T, E, F, I>(
     f: F,
     item: Result<U, E>,
@@ -118,39 +126,202 @@ pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
         .collect()
 }
 
-/* fn draw_grid(grid: &Parsed) {
-    let mut min_x = u64::MAX;
-    let mut max_x = 0;
-    let mut max_y = 0;
+const PALETTE: Palette = &[
+    ('.', BACKGROUND),
+    ('#', [0x8c, 0x7b, 0x6b]),
+    ('o', [0xf2, 0xd2, 0x6b]),
+    ('+', [0xe0, 0x4f, 0x3f]),
+];
+
+/// Dense bitmap, row by row
+#[derive(Debug, Clone)]
+struct Bits {
+    /// Number of words in a row
+    words: usize,
+    bits: Vec<u64>,
+}
 
-    for &(x, y) in grid.keys() {
-        if x < min_x {
-            min_x = x;
+impl Bits {
+    fn new(width: usize, height: usize) -> Self {
+        let words = width.div_ceil(64);
+        Bits {
+            words,
+            bits: vec![0; words * height],
         }
-        if x > max_x {
-            max_x = x;
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.bits[y * self.words + x / 64] & (1 << (x % 64)) != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.bits[y * self.words + x / 64] |= 1 << (x % 64);
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.bits[y * self.words..(y + 1) * self.words]
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.bits.iter().enumerate().flat_map(move |(i, &word)| {
+            let (y, x) = (i / self.words, (i % self.words) * 64);
+            (0..64)
+                .filter(move |b| word & (1 << b) != 0)
+                .map(move |b| (x + b, y))
+        })
+    }
+}
+
+/// The cave over the columns the sand can reach, without the floor
+#[derive(Debug, Clone)]
+struct Cave {
+    /// Cave coordinate of the first column, negative with a floor deeper than the source
+    min_x: i64,
+    width: usize,
+    height: usize,
+    floor: bool,
+    rock: Bits,
+    /// Rock and sand
+    filled: Bits,
+}
+
+impl Cave {
+    /// Without a floor sand falls out of the cave after the lowest rock or outside the
+    /// rocks columns, which always include the source. With a floor sand can go as far
+    /// sideways as it goes down.
+    fn new(rocks: &Parsed, floor: bool) -> Self {
+        let max_y = rocks.keys().map(|&(_, y)| y).max().unwrap();
+        let (min_x, max_x) = rocks
+            .keys()
+            .map(|&(x, _)| x as i64)
+            .minmax()
+            .into_option()
+            .unwrap();
+        let (mut min_x, mut max_x) = (min_x.min(500), max_x.max(500));
+        let height = if floor {
+            let floor_y = max_y + 2;
+            min_x = min_x.min(500 - floor_y as i64);
+            max_x = max_x.max(500 + floor_y as i64);
+            floor_y
+        } else {
+            max_y + 1
+        };
+
+        let (width, height) = ((max_x - min_x + 1) as usize, height as usize);
+        let mut rock = Bits::new(width, height);
+        for &(x, y) in rocks.keys() {
+            rock.set((x as i64 - min_x) as usize, y as usize);
+        }
+
+        Cave {
+            min_x,
+            width,
+            height,
+            floor,
+            filled: rock.clone(),
+            rock,
+        }
+    }
+
+    fn source(&self) -> usize {
+        (500 - self.min_x) as usize
+    }
+
+    fn sand(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.filled.iter().filter(|&(x, y)| !self.rock.get(x, y))
+    }
+
+    fn frame(&self, caption: String) -> Frame {
+        let mut frame = Frame::new(caption)
+            .with_background('.')
+            .with_palette(PALETTE);
+        let min_x = self.min_x;
+        for (x, y) in self.rock.iter() {
+            frame.set(x as i64 + min_x, y as _, '#');
+        }
+        for (x, y) in self.sand() {
+            frame.set(x as i64 + min_x, y as _, 'o');
+        }
+        if !self.filled.get(self.source(), 0) {
+            frame.set(500, 0, '+');
         }
 
-        if y > max_y {
-            max_y = y;
+        if let (true, Some(((min_x, _), (max_x, _)))) = (self.floor, frame.bounds()) {
+            for x in min_x - 1..=max_x + 1 {
+                frame.set(x, self.height as _, '#');
+            }
         }
+
+        frame.focus(500, 0);
+        frame
     }
+}
 
-    for y in 0..=(max_y + 1) {
-        for x in min_x..=(max_x + 1) {
-            if (x, y) != (500, 0) {
-                match grid.get(&(x, y)) {
-                    Some(Blocker::Rock) => print!("#"),
-                    Some(Blocker::Sand) => print!("o"),
-                    None => print!("."),
+/// Pours sand until a grain falls out of the cave or the source is covered, calling
+/// `settled` with the number of grains after each one comes to rest.
+///
+/// Every grain follows the path of the previous one up to where it came to rest, so
+/// the path is kept as a stack and each grain starts from the top of it.
+fn pour(cave: &mut Cave, mut settled: impl FnMut(&Cave, usize)) -> usize {
+    let mut path = vec![(cave.source(), 0)];
+    let mut count = 0;
+
+    while let Some(&(x, y)) = path.last() {
+        let next = if y + 1 == cave.height {
+            if !cave.floor {
+                return count;
+            }
+            None
+        } else {
+            let mut next = None;
+            for next_x in [Some(x), x.checked_sub(1), Some(x + 1)] {
+                match next_x.filter(|&x| x < cave.width) {
+                    None => return count,
+                    Some(next_x) if !cave.filled.get(next_x, y + 1) => {
+                        next = Some((next_x, y + 1));
+                        break;
+                    }
+                    Some(_) => (),
                 }
-            } else {
-                print!("+")
             }
+            next
+        };
+
+        match next {
+            Some(next) => path.push(next),
+            None => {
+                cave.filled.set(x, y);
+                path.pop();
+                count += 1;
+                settled(cave, count);
+            }
+        }
+    }
+
+    count
+}
+
+/// Counts the sand resting on the floor without dropping it.
+///
+/// With a floor every cell sand can fall to ends up filled, so each row of sand is the
+/// one above spread by a column on each side, without the rocks.
+fn flood(cave: &Cave) -> usize {
+    let mut row = vec![0; cave.rock.words];
+    row[cave.source() / 64] = 1 << (cave.source() % 64);
+    let mut count = 1;
+
+    for y in 1..cave.height {
+        let above = row.clone();
+        for (i, (word, &rock)) in row.iter_mut().zip(cave.rock.row(y)).enumerate() {
+            let before = i.checked_sub(1).map_or(0, |i| above[i] >> 63);
+            let after = above.get(i + 1).map_or(0, |w| w << 63);
+            *word = (above[i] | above[i] << 1 | before | above[i] >> 1 | after) & !rock;
         }
-        println!()
+        count += row.iter().map(|w| w.count_ones() as usize).sum::<usize>();
     }
-} */
+
+    count
+}
 
 fn find_shelves(grid: &Parsed) -> ((u64, u64), Vec<u64>) {
     let mut min_x = u64::MAX;
@@ -206,18 +377,6 @@ fn lay_sand_abyss(grid: &mut Parsed, shelves: &[u64], min_x: u64, max_x: u64) ->
     }
 }
 
-pub fn part1(mut input: Parsed) {
-    let ((min_x, max_x), shelves) = find_shelves(&input);
-
-    let mut sand_count = 0;
-
-    while lay_sand_abyss(&mut input, &shelves, min_x, max_x) {
-        sand_count += 1;
-    }
-
-    print_res!("Sand count: {sand_count}");
-}
-
 fn lay_sand_floor(grid: &mut Parsed, floor_y: u64) {
     let (mut x, mut y) = (500, 0);
 
@@ -239,35 +398,115 @@ fn lay_sand_floor(grid: &mut Parsed, floor_y: u64) {
     }
 }
 
-pub fn part2(mut input: Parsed) {
-    let max_y = input.keys().map(|&(_, y)| y).max().unwrap();
-
+/// Drops every grain from the source on a map of the cave, used to check [`sand_count`]
+pub fn sand_count_naive(mut input: Parsed, floor: bool) -> usize {
     let mut sand_count = 0;
-    while !input.contains_key(&(500, 0)) {
-        lay_sand_floor(&mut input, max_y + 2);
-        sand_count += 1;
+    if floor {
+        let max_y = input.keys().map(|&(_, y)| y).max().unwrap();
+        while !input.contains_key(&(500, 0)) {
+            lay_sand_floor(&mut input, max_y + 2);
+            sand_count += 1;
+        }
+    } else {
+        let ((min_x, max_x), shelves) = find_shelves(&input);
+        while lay_sand_abyss(&mut input, &shelves, min_x, max_x) {
+            sand_count += 1;
+        }
     }
+    sand_count
+}
+
+/// Number of grains resting before the sand falls out, or covers the source with a floor
+pub fn sand_count(input: &Parsed, floor: bool) -> usize {
+    let mut cave = Cave::new(input, floor);
+    if floor && !visualize::enabled() {
+        return flood(&cave);
+    }
+
+    let part = if floor { 2 } else { 1 };
+    pour(&mut cave, |cave, count| {
+        visualize::frame(|| cave.frame(format!("Part {part}: {count} sand")))
+    })
+}
 
+pub fn part1(input: Parsed) {
+    let sand_count = sand_count(&input, false);
+    print_res!("Sand count: {sand_count}");
+}
+
+pub fn part2(input: Parsed) {
+    let sand_count = sand_count(&input, true);
     print_res!("Sand count: {sand_count}");
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{flood, parsing, part1, part2, pour, sand_count, sand_count_naive, Cave};
+    use crate::{output::capture, visualize};
+    use indoc::indoc;
+
+    const EXAMPLE: &str = indoc! {"
+        498,4 -> 498,6 -> 496,6
+        503,4 -> 502,4 -> 502,9 -> 494,9
+    "};
+
+    #[test]
+    fn example() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(capture(|| part1(input.clone())).1, "Sand count: 24\n");
+        assert_eq!(capture(|| part2(input)).1, "Sand count: 93\n");
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn matches_naive() {
+        for input in [
+            EXAMPLE,
+            "500,2 -> 500,3\n",
+            "495,8 -> 505,8\n497,5 -> 498,5\n",
+            "490,20 -> 510,20 -> 510,2\n480,25 -> 485,25\n",
+            "498,2 -> 499,2\n501,2 -> 502,2\n",
+            // Rocks on one side of the source
+            "510,3 -> 512,3\n",
+            "488,3 -> 490,3 -> 490,1\n",
+        ] {
+            let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+            for floor in [false, true] {
+                let naive = sand_count_naive(input.clone(), floor);
+                assert_eq!(
+                    sand_count(&input, floor),
+                    naive,
+                    "{input:?}, floor: {floor}"
+                );
+
+                // Grains are poured one by one instead of flooding the floor when recording
+                let (count, frames) = visualize::record(|| sand_count(&input, floor));
+                assert_eq!((count, frames.len()), (naive, naive));
+            }
+        }
+    }
 
-    Ok(())
+    #[test]
+    fn deep_floor() {
+        // The sand spreads further left than x = 0 before reaching the floor, and fills
+        // every cell but the rock and the one it shelters
+        let input = parsing(&b"499,600 -> 501,600\n".to_vec().into()).unwrap();
+        let cave = Cave::new(&input, true);
+        assert_eq!(cave.min_x, -102);
+
+        let expected = 602 * 602 - 4;
+        assert_eq!(flood(&cave), expected);
+        assert_eq!(pour(&mut cave.clone(), |_, _| ()), expected);
+    }
 }
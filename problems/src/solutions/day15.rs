@@ -1,8 +1,8 @@
-use std::{collections::HashSet, time::Instant};
+use std::time::Instant;
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
-use itertools::Itertools;
+use tracing::error;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Sensor {
@@ -12,6 +12,8 @@ pub struct Sensor {
 
 type Parsed = Vec<Sensor>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .lines()
@@ -79,7 +81,10 @@ fn covered_intervals(sensors: &[Sensor], y: i64) -> Vec<(i64, i64)> {
         }
     }
 
-    intervals.sort_by(|a, b| a.0.cmp(&b.0));
+    if intervals.is_empty() {
+        return intervals;
+    }
+    intervals.sort_by_key(|a| a.0);
 
     let mut merged_intervals = Vec::new();
     let mut current_start = intervals[0].0;
@@ -101,60 +106,149 @@ fn covered_intervals(sensors: &[Sensor], y: i64) -> Vec<(i64, i64)> {
     merged_intervals
 }
 
-pub fn part1(input: Parsed) {
-    //let y = 10;
-    let y = 2000000;
+/// Row checked by part 1, 10 in the example
+pub const ROW: i64 = 2000000;
 
-    let merged_intervals = covered_intervals(&input, y);
+/// Coordinates of the distress beacon are between 0 and this, 20 in the example
+pub const BOUND: i64 = 4000000;
 
-    let amount: i64 = merged_intervals
+/// Positions of row `y` where there can't be a beacon
+pub fn cleared_squares(sensors: &[Sensor], y: i64) -> i64 {
+    covered_intervals(sensors, y)
         .iter()
         .map(|(start, end)| end - start + 1)
-        .sum();
+        .sum()
+}
 
-    print_res!("Cleared squares in line y={y}: {amount}");
+pub fn part1(input: Parsed) {
+    let amount = cleared_squares(&input, ROW);
+    print_res!("Cleared squares in line y={ROW}: {amount}");
 }
 
-pub fn part2(input: Parsed) {
-    let mut possible_positions: HashSet<(i64, i64)> = HashSet::new();
-
-    for y in 0..=4000000 {
-        let intervals = covered_intervals(&input, y);
-        if intervals.len() > 1 {
-            intervals
-                .iter()
-                .tuple_windows()
-                .flat_map(|(&(_, end), &(start, _))| (end + 1)..(start))
-                .for_each(|x| {
-                    possible_positions.insert((x, y));
-                });
-        }
+/// Finds the only position in the square from (0, 0) to (`bound`, `bound`) that no
+/// sensor covers.
+///
+/// The neighbours of this position are covered, so it is just outside the range of
+/// some sensors. It is then on the ±45° lines bordering their ranges, either where two
+/// of them cross or where one meets the edge of the square.
+pub fn distress_beacon(sensors: &[Sensor], bound: i64) -> Option<(i64, i64)> {
+    // Lines are x - y = a for the rising ones, and x + y = b for the falling ones
+    let mut rising = Vec::new();
+    let mut falling = Vec::new();
+    for sensor in sensors {
+        let (x, y) = sensor.pos;
+        let outside = mh_distance(sensor.pos, sensor.beacon) as i64 + 1;
+        rising.extend([x - y - outside, x - y + outside]);
+        falling.extend([x + y - outside, x + y + outside]);
     }
 
-    for sensor in &input {
-        possible_positions.remove(&sensor.beacon);
+    let crossings = rising.iter().flat_map(|&a| {
+        falling
+            .iter()
+            .filter(move |&&b| (a + b) % 2 == 0)
+            .map(move |&b| ((a + b) / 2, (b - a) / 2))
+    });
+    let on_edges = rising.iter().flat_map(|&a| {
+        [0, bound]
+            .into_iter()
+            .flat_map(move |edge| [(edge, edge - a), (edge + a, edge)])
+    });
+    let on_edges = on_edges.chain(falling.iter().flat_map(|&b| {
+        [0, bound]
+            .into_iter()
+            .flat_map(move |edge| [(edge, b - edge), (b - edge, edge)])
+    }));
+    let corners = [(0, 0), (0, bound), (bound, 0), (bound, bound)];
+
+    crossings
+        .chain(on_edges)
+        .chain(corners)
+        .filter(|&(x, y)| (0..=bound).contains(&x) && (0..=bound).contains(&y))
+        .find(|&position| {
+            sensors.iter().all(|sensor| {
+                mh_distance(sensor.pos, position) > mh_distance(sensor.pos, sensor.beacon)
+            })
+        })
+}
+
+pub fn part2(input: Parsed) {
+    match distress_beacon(&input, BOUND) {
+        Some((x, y)) => print_res!("Tuning frequency is: {}", x * 4000000 + y),
+        None => error!("Every position is covered by a sensor"),
     }
-    let position = possible_positions.drain().next().unwrap();
-    print_res!("Tuning frequency is: {}", position.0 * 4000000 + position.1);
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cleared_squares, distress_beacon, mh_distance, parsing, Parsed};
+    use indoc::indoc;
+
+    const EXAMPLE: &str = indoc! {"
+        Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+        Sensor at x=9, y=16: closest beacon is at x=10, y=16
+        Sensor at x=13, y=2: closest beacon is at x=15, y=3
+        Sensor at x=12, y=14: closest beacon is at x=10, y=16
+        Sensor at x=10, y=20: closest beacon is at x=10, y=16
+        Sensor at x=14, y=17: closest beacon is at x=10, y=16
+        Sensor at x=8, y=7: closest beacon is at x=2, y=10
+        Sensor at x=2, y=0: closest beacon is at x=2, y=10
+        Sensor at x=0, y=11: closest beacon is at x=2, y=10
+        Sensor at x=20, y=14: closest beacon is at x=25, y=17
+        Sensor at x=17, y=20: closest beacon is at x=21, y=22
+        Sensor at x=16, y=7: closest beacon is at x=15, y=3
+        Sensor at x=14, y=3: closest beacon is at x=15, y=3
+        Sensor at x=20, y=1: closest beacon is at x=15, y=3
+    "};
+
+    /// Every position of the square that no sensor covers
+    fn uncovered(sensors: &Parsed, bound: i64) -> Vec<(i64, i64)> {
+        (0..=bound)
+            .flat_map(|x| (0..=bound).map(move |y| (x, y)))
+            .filter(|&p| {
+                sensors
+                    .iter()
+                    .all(|s| mh_distance(s.pos, p) > mh_distance(s.pos, s.beacon))
+            })
+            .collect()
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn example() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(cleared_squares(&input, 10), 26);
+        assert_eq!(uncovered(&input, 20), [(14, 11)]);
+        assert_eq!(distress_beacon(&input, 20), Some((14, 11)));
+    }
 
-    Ok(())
+    #[test]
+    fn edges() {
+        // Only the far corner is out of range
+        let input = "Sensor at x=0, y=0: closest beacon is at x=0, y=7\n";
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(distress_beacon(&input, 4), Some((4, 4)));
+
+        // The gap between the sensors reaches the top edge
+        let input = indoc! {"
+            Sensor at x=0, y=1: closest beacon is at x=0, y=4
+            Sensor at x=6, y=1: closest beacon is at x=6, y=4
+            Sensor at x=3, y=5: closest beacon is at x=3, y=9
+        "};
+        let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(uncovered(&input, 4), [(3, 0)]);
+        assert_eq!(distress_beacon(&input, 4), Some((3, 0)));
+
+        assert_eq!(distress_beacon(&input, 2), None);
+    }
 }
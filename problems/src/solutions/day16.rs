@@ -3,7 +3,7 @@ use std::{
     time::Instant,
 };
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BStr, BString, ByteSlice};
 use im::{vector, Vector};
 use itertools::Itertools;
@@ -18,7 +18,9 @@ pub struct Valve<'a> {
 
 type Parsed<'a> = Vec<Valve<'a>>;
 
-pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
+pub fn parsing(input: &BString) -> color_eyre::Result<Parsed<'_>> {
     let mut valves = HashMap::with_capacity(input.lines().count());
     let mut ordered = Vec::with_capacity(valves.capacity());
 
@@ -391,22 +393,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
@@ -3,8 +3,15 @@ use std::{
     time::Instant,
 };
 
-use crate::{load, print_res};
+use crate::{
+    load, print_res,
+    render::{Palette, BACKGROUND},
+    run_parts,
+    visualize::{self, Frame},
+    Implemented,
+};
 use bstr::{BString, ByteSlice};
+use tracing::trace;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Push {
@@ -24,6 +31,8 @@ impl Push {
 
 type Parsed = Vec<Push>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input.trim().iter().map(|&b| Push::from_byte(b)).collect()
 }
@@ -73,33 +82,45 @@ const PIECE_4: u32 = u32::from_be_bytes([
 const PIECES: &[u32] = &[PIECE_0, PIECE_1, PIECE_2, PIECE_3, PIECE_4];
 const HEIGHTS: &[usize] = &[1, 3, 3, 4, 2];
 
-#[allow(dead_code)]
-fn print_board(board: &[u8], current_piece: Option<(usize, u32)>) {
+const PALETTE: Palette = &[
+    ('.', BACKGROUND),
+    ('|', [0x6b, 0x6b, 0x7b]),
+    ('-', [0x6b, 0x6b, 0x7b]),
+    ('+', [0x6b, 0x6b, 0x7b]),
+    ('#', [0x9c, 0xb4, 0xd8]),
+    ('@', [0xf2, 0xa6, 0x5a]),
+];
+
+/// The board with the falling piece, rows are flipped so the tower grows up
+fn board_frame(board: &[u8], current_piece: Option<(usize, u32)>, caption: String) -> Frame {
+    let mut frame = Frame::new(caption).with_palette(PALETTE);
     let current_piece = current_piece.map(|(h, p)| (h, p.to_le_bytes()));
     for (height, line) in board.iter().enumerate().rev().take(board.len() - 1) {
-        print!("|");
+        let y = -(height as i64);
+        frame.set_text(0, y, "|.......|");
         for x in (0..7).rev() {
+            let column = 7 - x as i64;
             if 1 << x & line == 0 {
-                match current_piece {
-                    Some((h, mask)) => {
-                        if (height >= h && height < h + 4) && (1 << x & mask[height - h] != 0) {
-                            print!("@");
-                        } else {
-                            print!(".");
-                        }
+                if let Some((h, mask)) = current_piece {
+                    if (height >= h && height < h + 4) && (1 << x & mask[height - h] != 0) {
+                        frame.set(column, y, '@');
                     }
-                    _ => print!("."),
                 }
             } else {
-                print!("#")
+                frame.set(column, y, '#')
             }
         }
-        println!("| {height}")
     }
-    println!("+-------+")
+    frame.set_text(0, 0, "+-------+");
+
+    if let Some((h, _)) = current_piece {
+        frame.focus(4, -(h as i64));
+    }
+    frame
 }
 
-fn run_fall(moves: Parsed, amount: usize) -> usize {
+/// Height of the tower once `amount` rocks have come to rest
+pub fn run_fall(moves: Parsed, amount: usize) -> usize {
     let mut pieces = PIECES.iter().zip(HEIGHTS).enumerate().cycle().take(amount);
 
     let mut board = vec![255];
@@ -111,7 +132,7 @@ fn run_fall(moves: Parsed, amount: usize) -> usize {
 
     let mut cycled_highest_point = None;
 
-    // print_board(&board, None);
+    trace!("Empty board:\n{}", board_frame(&board, None, String::new()));
 
     let mut rock_count = 0;
 
@@ -120,7 +141,7 @@ fn run_fall(moves: Parsed, amount: usize) -> usize {
         let free_height = board.len() - highest_point;
         let height_needed = HEIGHT_OFFSET + 4;
         if free_height < height_needed {
-            board.extend(std::iter::repeat(0).take(height_needed - free_height));
+            board.extend(std::iter::repeat_n(0, height_needed - free_height));
         }
 
         let mut height = highest_point + HEIGHT_OFFSET;
@@ -158,7 +179,10 @@ fn run_fall(moves: Parsed, amount: usize) -> usize {
             }
         }
 
-        // print_board(&board, Some((height, mask)));
+        trace!(
+            "Spawned rock:\n{}",
+            board_frame(&board, Some((height, mask)), String::new())
+        );
 
         for (_, push) in moves.by_ref() {
             match push {
@@ -181,7 +205,17 @@ fn run_fall(moves: Parsed, amount: usize) -> usize {
                     }
                 }
             }
-            // print_board(&board, Some((height, mask)));
+            trace!(
+                "Pushed {push:?}:\n{}",
+                board_frame(&board, Some((height, mask)), String::new())
+            );
+            visualize::frame(|| {
+                board_frame(
+                    &board,
+                    Some((height, mask)),
+                    format!("Rock {}, pushed {push:?}", rock_count + 1),
+                )
+            });
 
             if mask_collision(mask, &board, height - 1) {
                 let current_piece_height = height + piece_height;
@@ -192,7 +226,7 @@ fn run_fall(moves: Parsed, amount: usize) -> usize {
                     .iter()
                     .zip(&mut board[height..height + 4])
                     .for_each(|(m, b)| *b |= m);
-                // print_board(&board, Some((height, mask)));
+                trace!("Rock rests:\n{}", board_frame(&board, None, String::new()));
                 rock_count += 1;
                 break;
             }
@@ -219,22 +253,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
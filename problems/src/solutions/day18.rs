@@ -3,7 +3,7 @@ use std::{
     time::Instant,
 };
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
 use itertools::Itertools;
 use petgraph::{
@@ -14,6 +14,8 @@ use petgraph::{
 
 type Parsed = HashSet<(i16, i16, i16)>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .to_str()?
@@ -118,22 +120,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
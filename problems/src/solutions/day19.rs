@@ -1,7 +1,8 @@
 use std::{collections::VecDeque, time::Instant};
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
+use tracing::debug;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Blueprint {
@@ -13,6 +14,8 @@ pub struct Blueprint {
 
 type Parsed = Vec<Blueprint>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .lines()
@@ -100,8 +103,8 @@ fn blueprint_count(blueprint: &Blueprint, duration: u8, trim_branch: u16) -> u8
         .ore
         .max(blueprint.clay)
         .max(blueprint.obsidian.0)
-        .max(blueprint.geode.0) as u8;
-    let max_clay = blueprint.obsidian.1 as u8;
+        .max(blueprint.geode.0);
+    let max_clay = blueprint.obsidian.1;
 
     //let mut seen = HashMap::new();
 
@@ -197,7 +200,7 @@ pub fn part1(input: Parsed) {
     let quality_levels: usize = input
         .iter()
         .enumerate()
-        .inspect(|(i, _)| println!("Running blueprint {}", i + 1))
+        .inspect(|(i, _)| debug!("Running blueprint {}", i + 1))
         .map(|(i, b)| (i + 1) * blueprint_count(b, 24, 2) as usize)
         .sum();
     print_res!("Quality level sum: {quality_levels}")
@@ -213,22 +216,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
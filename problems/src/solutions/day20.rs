@@ -1,10 +1,12 @@
 use std::time::Instant;
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
 
 type Parsed = Vec<(i64, u16)>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .lines()
@@ -83,6 +85,18 @@ pub fn part2(mut input: Parsed) {
     print_res!("Coordinates sum: {coordinates}");
 }
 
+pub fn main() -> color_eyre::Result<()> {
+    let context = load(IMPLEMENTED)?;
+
+    let start = Instant::now();
+    let parsed = parsing(&context.input)?;
+    let elapsed = start.elapsed();
+
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use itertools::Itertools;
@@ -173,24 +187,3 @@ mod test {
         assert_step(10, &input);
     }
 }
-
-pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
-
-    let start = Instant::now();
-    let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
-
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
-
-    Ok(())
-}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, str::FromStr, time::Instant};
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BStr, BString, ByteSlice};
 
 #[derive(Debug, Clone, Copy)]
@@ -35,7 +35,9 @@ type OperationMap<'a> = HashMap<u16, (&'a BStr, Operation)>;
 
 type Parsed<'a> = (u16, u16, OperationMap<'a>);
 
-pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
+pub fn parsing(input: &BString) -> color_eyre::Result<Parsed<'_>> {
     let mut monkeys = HashMap::new();
     for (i, line) in input.lines().enumerate() {
         let Some((name, sentence)) = line.split_once_str(":") else {
@@ -194,22 +196,13 @@ pub fn part2((root, human, mut monkeys): Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
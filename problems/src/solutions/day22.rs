@@ -1,10 +1,17 @@
-use std::{ops::Index, time::Instant};
+use std::{fmt::Write, ops::Index, time::Instant};
 
-use crate::{load, print_res};
+use crate::{
+    load, print_res, run_parts,
+    visualize::{self, Frame},
+    Implemented,
+};
 use bstr::{BString, ByteSlice};
+use tracing::trace;
 
 type Parsed = (Vec2D<Tile>, Instructions);
 
+pub const IMPLEMENTED: Implemented = Implemented::Part1;
+
 #[derive(Clone, Debug)]
 pub struct Instructions {
     body: Vec<(u16, Turn)>,
@@ -17,14 +24,14 @@ pub enum Turn {
     CounterClockwise,
 }
 
-#[allow(dead_code)]
-fn print_board(board: &Vec2D<Tile>) {
+fn display_board(board: &Vec2D<Tile>) -> String {
+    let mut out = String::new();
     for row in board.rows() {
         for &item in row.iter() {
             match item {
-                Tile::Void => print!("         "),
-                Tile::Space => print!(",.......,"),
-                Tile::Wall => print!("#########"),
+                Tile::Void => out.push_str("         "),
+                Tile::Space => out.push_str(",.......,"),
+                Tile::Wall => out.push_str("#########"),
                 Tile::Wraparound(Wraparound {
                     turn: _,
                     vertical,
@@ -34,18 +41,54 @@ fn print_board(board: &Vec2D<Tile>) {
                         None => "---".into(),
                         Some(v) => v.to_string(),
                     };
-                    print!(
+                    write!(
+                        out,
                         "|{:>2},{:>2}|{:>2},{:>2}|",
                         opt_repr(vertical.map(|v| v.0)),
                         opt_repr(vertical.map(|v| v.1)),
                         opt_repr(horizontal.map(|v| v.0)),
                         opt_repr(horizontal.map(|v| v.1)),
                     )
+                    .unwrap();
                 }
             }
         }
-        println!()
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+fn facing_char(direction: (i32, i32)) -> char {
+    match direction {
+        (1, 0) => '>',
+        (-1, 0) => '<',
+        (0, -1) => '^',
+        (0, 1) => 'v',
+        _ => unreachable!(),
+    }
+}
+
+/// The board with the positions and facings reached after each instruction
+fn path_frame(board: &Vec2D<Tile>, path: &[(i32, i32, char)], caption: String) -> Frame {
+    let mut frame = Frame::new(caption);
+    for (y, row) in board.rows().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            match tile {
+                Tile::Space => frame.set(x as _, y as _, '.'),
+                Tile::Wall => frame.set(x as _, y as _, '#'),
+                Tile::Void | Tile::Wraparound(_) => (),
+            }
+        }
+    }
+
+    for &(x, y, facing) in path {
+        frame.set(x as _, y as _, facing);
     }
+    if let Some(&(x, y, _)) = path.last() {
+        frame.focus(x as _, y as _);
+    }
+
+    frame
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -290,8 +333,8 @@ fn move_in_direction(
         let ny = y + direction.1;
         match board[(nx as usize, ny as usize)] {
             Tile::Space => {
-                x = nx as i32;
-                y = ny as i32;
+                x = nx;
+                y = ny;
             }
             Tile::Wall => break,
             Tile::Wraparound(Wraparound {
@@ -307,11 +350,11 @@ fn move_in_direction(
                 let nx: i16;
                 let ny: i16;
                 if direction.0 != 0 {
-                    //println!("Horizontal wrap from {nx}/{ny} ({horizontal:?})");
+                    trace!("Horizontal wrap from {x}/{y} ({horizontal:?})");
                     let (wx, wy) = horizontal.unwrap();
                     (nx, ny) = (wx as _, wy as _);
                 } else {
-                    //println!("Vertical wrap from {nx}/{ny} ({vertical:?})");
+                    trace!("Vertical wrap from {x}/{y} ({vertical:?})");
                     let (wx, wy) = vertical.unwrap();
                     (nx, ny) = (wx as _, wy as _);
                 }
@@ -332,7 +375,7 @@ fn move_in_direction(
 }
 
 pub fn part1((board, movements): Parsed) {
-    //print_board(&board);
+    trace!("Board:\n{}", display_board(&board));
     let start = board
         .row(1)
         .iter()
@@ -342,9 +385,16 @@ pub fn part1((board, movements): Parsed) {
     let mut x = start.0 as i32;
     let mut y = 1;
     let mut direction = (1, 0);
+    let mut path = Vec::new();
     for (amount, turn) in movements.body {
         (x, y) = move_in_direction(direction, x, y, amount, &board);
         direction = apply_turn(direction, turn);
+        if visualize::enabled() {
+            path.push((x, y, facing_char(direction)));
+            visualize::frame(|| {
+                path_frame(&board, &path, format!("Moved {amount}, turned {turn:?}"))
+            });
+        }
     }
     (x, y) = move_in_direction(direction, x, y, movements.last, &board);
 
@@ -364,27 +414,18 @@ pub fn part1((board, movements): Parsed) {
     print_res!("Final password is: {final_password}");
 }
 
-pub fn part2(input: Parsed) {
+pub fn part2(_input: Parsed) {
     todo!("todo part2")
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
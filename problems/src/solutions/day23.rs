@@ -1,11 +1,20 @@
 use std::{collections::HashMap, time::Instant};
 
-use crate::{load, print_res};
+use crate::{
+    load, print_res,
+    render::{Palette, BACKGROUND},
+    run_parts,
+    visualize::{self, Frame},
+    Implemented,
+};
 use bstr::{BString, ByteSlice};
 use fnv::FnvHashSet;
+use tracing::trace;
 
 type Parsed = FnvHashSet<(i64, i64)>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .lines()
@@ -23,18 +32,16 @@ pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
         .collect()
 }
 
-#[allow(dead_code)]
-fn print_board(board: &Parsed, min_x: i64, max_x: i64, min_y: i64, max_y: i64) {
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            if board.contains(&(x, y)) {
-                print!("#")
-            } else {
-                print!(".")
-            }
-        }
-        println!()
+const PALETTE: Palette = &[('.', BACKGROUND), ('#', [0x5f, 0xc2, 0x6b])];
+
+fn board_frame(board: &Parsed, caption: String) -> Frame {
+    let mut frame = Frame::new(caption)
+        .with_background('.')
+        .with_palette(PALETTE);
+    for &(x, y) in board {
+        frame.set(x, y, '#');
     }
+    frame
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -105,7 +112,7 @@ fn round(input: &Parsed, directions: &mut [Direction; 4]) -> (Parsed, bool) {
             }
         }
         if !inserted {
-            // println!("Could not move (all taken) {x}/{y}");
+            trace!("Could not move (all taken) {x}/{y}");
             new_board.insert((x, y));
         }
     }
@@ -114,12 +121,12 @@ fn round(input: &Parsed, directions: &mut [Direction; 4]) -> (Parsed, bool) {
 
     for ((nx, ny), old) in proposed {
         if old.len() == 1 {
-            // println!("Could move to {nx}/{ny} from {}/{}", old[0].0, old[0].1);
+            trace!("Could move to {nx}/{ny} from {}/{}", old[0].0, old[0].1);
             new_board.insert((nx, ny));
             moved = true;
         } else {
             old.iter().for_each(|&(x, y)| {
-                // println!("Could not move to {nx}/{ny} from {x}/{y}");
+                trace!("Could not move to {nx}/{ny} from {x}/{y}");
                 new_board.insert((x, y));
             })
         }
@@ -130,19 +137,20 @@ fn round(input: &Parsed, directions: &mut [Direction; 4]) -> (Parsed, bool) {
 }
 
 pub fn part1(mut input: Parsed) {
-    //print_board(&input, 0, 4, 0, 5);
-    //print_board(&input, -3, 10, -2, 9);
+    trace!("Initial board:\n{}", board_frame(&input, String::new()));
     let mut directions = [
         Direction::North,
         Direction::South,
         Direction::West,
         Direction::East,
     ];
-    for _ in 0..10 {
+    for round_idx in 1..=10 {
         (input, _) = round(&input, &mut directions);
-        //println!("------");
-        //print_board(&input, 0, 4, 0, 5);
-        //print_board(&input, -3, 10, -2, 9);
+        trace!(
+            "Board after round {round_idx}:\n{}",
+            board_frame(&input, String::new())
+        );
+        visualize::frame(|| board_frame(&input, format!("Part 1: round {round_idx}")));
     }
 
     let mut min_x = i64::MAX;
@@ -174,10 +182,23 @@ pub fn part2(mut input: Parsed) {
     while moved {
         (input, moved) = round(&input, &mut directions);
         count += 1;
+        visualize::frame(|| board_frame(&input, format!("Part 2: round {count}")));
     }
     print_res!("Number of rounds: {count}");
 }
 
+pub fn main() -> color_eyre::Result<()> {
+    let context = load(IMPLEMENTED)?;
+
+    let start = Instant::now();
+    let parsed = parsing(&context.input)?;
+    let elapsed = start.elapsed();
+
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::{parsing, Direction};
@@ -251,24 +272,3 @@ mod test {
         assert_eq!(state, step);
     }
 }
-
-pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
-
-    let start = Instant::now();
-    let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
-
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
-
-    Ok(())
-}
@@ -3,10 +3,17 @@ use std::{
     time::Instant,
 };
 
-use crate::{load, print_res};
+use crate::{
+    load, print_res,
+    render::{Palette, Rgb, BACKGROUND},
+    run_parts,
+    visualize::{self, Frame},
+    Implemented,
+};
 use bstr::{BString, ByteSlice};
 use itertools::Itertools;
 use tinyvec::ArrayVec;
+use tracing::trace;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Blizzard {
@@ -34,6 +41,8 @@ impl Blizzard {
 
 type Parsed = (Box<[Box<[Blizzard]>]>, Box<[Box<[Blizzard]>]>);
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 // y - |y-(t % 2y)|
 
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
@@ -90,8 +99,25 @@ pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
 
 type BlizzardContainer<'a> = &'a [Box<[Blizzard]>];
 
-#[allow(dead_code)]
-fn print_board(rows: BlizzardContainer, cols: BlizzardContainer, t: u64) {
+const BLIZZARD: Rgb = [0x7f, 0xb8, 0xe6];
+const BLIZZARDS: Rgb = [0x3f, 0x78, 0xc6];
+
+const PALETTE: Palette = &[
+    ('.', BACKGROUND),
+    ('#', [0x8c, 0x8c, 0x9c]),
+    ('E', [0xf2, 0x5a, 0x5a]),
+    ('<', BLIZZARD),
+    ('>', BLIZZARD),
+    ('^', BLIZZARD),
+    ('v', BLIZZARD),
+    ('2', BLIZZARDS),
+    ('3', BLIZZARDS),
+    ('4', BLIZZARDS),
+    ('*', BLIZZARDS),
+];
+
+/// The valley at minute `t`, the valley itself starts at `(1, 1)` inside the walls
+fn board_frame(rows: BlizzardContainer, cols: BlizzardContainer, t: u64, caption: String) -> Frame {
     let mut positions = HashMap::new();
     for (b, pos) in rows_positions(rows, cols.len() as _, t) {
         positions
@@ -107,18 +133,31 @@ fn print_board(rows: BlizzardContainer, cols: BlizzardContainer, t: u64) {
             .push(b.repr(true));
     }
 
-    for y in 0..rows.len() {
-        print!("#");
-        for x in 0..cols.len() {
-            match positions.get(&(x as _, y as _)).map(|v| v.as_slice()) {
-                None => print!("."),
-                Some([x]) => print!("{x}"),
-                Some(v) if v.len() < 10 => print!("{}", v.len()),
-                Some(_) => print!("*"),
-            }
-        }
-        println!("#");
+    let (width, height) = (cols.len() as i64, rows.len() as i64);
+    let mut frame = Frame::new(caption)
+        .with_background('.')
+        .with_palette(PALETTE);
+    for x in 0..width + 2 {
+        frame.set(x, 0, '#');
+        frame.set(x, height + 1, '#');
+    }
+    for y in 0..height + 2 {
+        frame.set(0, y, '#');
+        frame.set(width + 1, y, '#');
     }
+    frame.set(1, 0, '.');
+    frame.set(width, height + 1, '.');
+
+    for (&(x, y), blizzards) in &positions {
+        let c = match blizzards.as_slice() {
+            [c] => *c,
+            v if v.len() < 10 => char::from_digit(v.len() as u32, 10).unwrap(),
+            _ => '*',
+        };
+        frame.set(x as i64 + 1, y as i64 + 1, c);
+    }
+
+    frame
 }
 
 fn row_positions(
@@ -132,7 +171,7 @@ fn row_positions(
 }
 
 fn rows_positions(
-    rows: BlizzardContainer,
+    rows: BlizzardContainer<'_>,
     row_len: u16,
     t: u64,
 ) -> impl Iterator<Item = (Blizzard, (i16, i16))> + '_ {
@@ -152,7 +191,7 @@ fn col_positions(
 }
 
 fn cols_positions(
-    cols: BlizzardContainer,
+    cols: BlizzardContainer<'_>,
     col_len: u16,
     t: u64,
 ) -> impl Iterator<Item = (Blizzard, (i16, i16))> + '_ {
@@ -201,11 +240,24 @@ fn time_from(
 
     let mut visited = HashSet::new();
 
+    let mut minute = None;
     while let Some((x, y, t)) = paths.pop_front() {
         if !visited.insert((x, y, t % total_mod)) {
             continue;
         }
 
+        // The search is breadth first, so the queue holds every position reachable at `t`
+        if minute != Some(t) {
+            minute = Some(t);
+            visualize::frame(|| {
+                let mut frame = board_frame(rows, cols, t, format!("Minute {t}"));
+                for &(x, y, _) in std::iter::once(&(x, y, t)).chain(&paths) {
+                    frame.set(x as i64 + 1, y as i64 + 1, 'E');
+                }
+                frame
+            });
+        }
+
         if x == dx && y == dy {
             return t;
         }
@@ -219,9 +271,14 @@ fn time_from(
 }
 
 pub fn part1((rows, cols): Parsed) {
-    /* print_board(&rows, &cols, 0);
-    println!("----");
-    print_board(&rows, &cols, 1); */
+    trace!(
+        "Blizzards at minute 0:\n{}",
+        board_frame(&rows, &cols, 0, String::new())
+    );
+    trace!(
+        "Blizzards at minute 1:\n{}",
+        board_frame(&rows, &cols, 1, String::new())
+    );
 
     print_res!(
         "Number of minutes to traverse: {}",
@@ -249,22 +306,13 @@ pub fn part2((rows, cols): Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
@@ -1,10 +1,12 @@
 use std::time::Instant;
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
 
 type Parsed = Vec<i64>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Part1;
+
 fn parse_snafu(num: &[u8]) -> color_eyre::Result<i64> {
     num.iter()
         .rev()
@@ -93,27 +95,18 @@ pub fn part1(input: Parsed) {
     print_res!("Result is: {}", to_snafu(sum))
 }
 
-pub fn part2(input: Parsed) {
+pub fn part2(_input: Parsed) {
     todo!("todo part2")
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
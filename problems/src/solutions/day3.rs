@@ -1,11 +1,13 @@
 use std::time::Instant;
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BStr, BString, ByteSlice};
 use itertools::Itertools;
 
 type Parsed<'a> = Vec<(Vec<u8>, Vec<u8>)>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 fn comp(s: &BStr) -> Vec<u8> {
     s.iter()
         .map(|&i| match i {
@@ -16,7 +18,7 @@ fn comp(s: &BStr) -> Vec<u8> {
         .collect()
 }
 
-pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
+pub fn parsing(input: &BString) -> color_eyre::Result<Parsed<'_>> {
     Ok(input
         .trim()
         .split_str("\n")
@@ -87,22 +89,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
@@ -1,16 +1,18 @@
 use std::{collections::VecDeque, time::Instant};
 
-use crate::{load, print_res, print_res_part};
+use crate::{load, print_res, print_res_part, run_parts, Implemented};
 use bstr::{BString, ByteSlice};
 
 type Parsed = (Vec<VecDeque<u8>>, Vec<(u8, u8, u8)>);
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     let Some((crates, instructions)) = input.split_once_str("\n\n") else {
         color_eyre::eyre::bail!("Malformed input, has no empty line");
     };
 
-    let Some(last_line) = crates.lines().last() else {
+    let Some(last_line) = crates.lines().next_back() else {
         color_eyre::eyre::bail!("Crate input is empty");
     };
     let stack_count = last_line.to_str()?.trim().split_ascii_whitespace().count();
@@ -95,22 +97,40 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{parsing, part1, part2};
+    use crate::output::capture;
+    use indoc::indoc;
+
+    const EXAMPLE: &str = indoc! {"
+            [D]    
+        [N] [C]    
+        [Z] [M] [P]
+         1   2   3 
+
+        move 1 from 2 to 1
+        move 3 from 1 to 3
+        move 2 from 2 to 1
+        move 1 from 1 to 2
+    "};
+
+    #[test]
+    fn example() {
+        let input = parsing(&EXAMPLE.as_bytes().to_vec().into()).unwrap();
+
+        assert_eq!(capture(|| part1(input.clone())).1, "Crates are: CMZ\n");
+        assert_eq!(capture(|| part2(input)).1, "Crates are: MCD\n");
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, time::Instant};
 
-use crate::{load, print_res};
+use crate::{load, print_res, run_parts, Implemented};
 use bstr::{BStr, BString, ByteSlice};
 
 #[derive(Debug, Clone, Copy)]
@@ -30,7 +30,9 @@ pub enum Command<'a> {
 
 type Parsed<'a> = Vec<Command<'a>>;
 
-pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
+pub fn parsing(input: &BString) -> color_eyre::Result<Parsed<'_>> {
     input
         .split_str("$ ")
         .skip(1)
@@ -212,22 +214,13 @@ pub fn part2(input: Parsed) {
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
-
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
-    }
-    let elapsed_part = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
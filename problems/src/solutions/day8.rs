@@ -1,23 +1,17 @@
 use std::time::Instant;
 
-use crate::{load, print_res};
-use bstr::{BString, ByteSlice};
-use ndarray::Array2;
+use crate::{
+    grid::parse_grid, load, monotonic::next_greater_by, print_res, run_parts, Implemented,
+};
+use bstr::BString;
+use ndarray::{s, Array2, ArrayView1, ArrayViewMut1, Axis};
 
 type Parsed = Array2<u8>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
-    let mut line_count = 0;
-    let mut line_len = 0;
-    let iter = input
-        .lines()
-        .flat_map(|line| {
-            line_len = line.len();
-            line_count += 1;
-            line.iter().map(|i| i - b'0')
-        })
-        .collect();
-    Array2::from_shape_vec((line_count, line_len), iter).map_err(Into::into)
+    Ok(parse_grid(input, |c| c.is_ascii_digit().then(|| c - b'0'))?)
 }
 
 pub fn part1(input: Parsed) {
@@ -67,8 +61,9 @@ pub fn part1(input: Parsed) {
     print_res!("Visible tree count: {visible_count}");
 }
 
-pub fn part2(input: Parsed) {
-    let mut scenic_score: Array2<u64> = Array2::ones((input.ncols(), input.nrows()));
+/// Scenic scores by looking along each direction from every tree, in O(n³)
+pub fn scenic_scores_naive(input: &Parsed) -> Array2<u64> {
+    let mut scenic_score: Array2<u64> = Array2::ones(input.dim());
 
     fn scenic_score_dir(
         current_tree: u8,
@@ -113,27 +108,116 @@ pub fn part2(input: Parsed) {
         }
     }
 
-    let max_score = scenic_score.iter().max().unwrap();
+    scenic_score
+}
+
+/// Multiplies the scores by the viewing distance towards the end of the lane,
+/// which is the distance to the next tree at least as high, or to the edge
+fn view_along(mut scores: ArrayViewMut1<u64>, trees: ArrayView1<u8>) {
+    let trees = trees.to_vec();
+    let blocking = next_greater_by(&trees, |current, next| next >= current);
+
+    for (i, (score, blocking)) in scores.iter_mut().zip(blocking).enumerate() {
+        *score *= (blocking.unwrap_or(trees.len() - 1) - i) as u64;
+    }
+}
+
+/// Scenic scores with a monotonic stack for each direction, in O(n²)
+pub fn scenic_scores(input: &Parsed) -> Array2<u64> {
+    let mut scores = Array2::ones(input.dim());
+
+    for axis in [Axis(0), Axis(1)] {
+        for (mut scores, trees) in scores.lanes_mut(axis).into_iter().zip(input.lanes(axis)) {
+            view_along(scores.view_mut(), trees);
+            view_along(scores.slice_mut(s![..;-1]), trees.slice(s![..;-1]));
+        }
+    }
+
+    scores
+}
+
+pub fn part2(input: Parsed) {
+    let max_score = scenic_scores(&input).into_iter().max().unwrap();
     print_res!("Max score is: {max_score}");
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    run_parts(context.part, elapsed, parsed, part1, part2);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parsing, part1, part2, scenic_scores, scenic_scores_naive};
+    use crate::output::capture_parts;
+    use indoc::indoc;
+
+    #[test]
+    fn example() {
+        let input = indoc! {"
+            30373
+            25512
+            65332
+            33549
+            35390
+        "};
+        assert_eq!(
+            capture_parts(input, parsing, part1, part2),
+            (
+                "Visible tree count: 21\n".into(),
+                "Max score is: 8\n".into()
+            )
+        );
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    #[test]
+    fn rectangular() {
+        let wide = "30373\r\n25512\r\n65332";
+        assert_eq!(
+            capture_parts(wide, parsing, part1, part2),
+            (
+                "Visible tree count: 14\n".into(),
+                "Max score is: 2\n".into()
+            )
+        );
+
+        let tall = "326\n055\n353\n713\n322\n";
+        assert_eq!(
+            capture_parts(tall, parsing, part1, part2),
+            (
+                "Visible tree count: 14\n".into(),
+                "Max score is: 2\n".into()
+            )
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn monotonic_matches_naive() {
+        for input in [
+            "30373\n25512\n65332\n33549\n35390\n",
+            "30373\n25512\n65332\n",
+            "326\n055\n353\n713\n322\n",
+            "5\n",
+            "999\n999\n",
+        ] {
+            let input = parsing(&input.as_bytes().to_vec().into()).unwrap();
+            assert_eq!(scenic_scores(&input), scenic_scores_naive(&input));
+        }
+    }
+
+    #[test]
+    fn ragged() {
+        let err = parsing(&b"303\n25\n653\n".to_vec().into()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 2 has 2 cells, expected 3 like the first line"
+        );
+    }
 }
@@ -1,7 +1,15 @@
-use std::{collections::HashSet, time::Instant};
+use std::{fmt::Write, path::Path, time::Instant};
 
-use crate::{load, print_res};
+use crate::{
+    load, print_res, run_parts,
+    visualize::{self, Frame},
+    Implemented, Part,
+};
 use bstr::{BString, ByteSlice};
+use color_eyre::eyre;
+use fnv::FnvHashSet;
+use serde::Serialize;
+use tracing::debug;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Direction {
@@ -13,6 +21,8 @@ pub enum Direction {
 
 type Parsed = Vec<(Direction, usize)>;
 
+pub const IMPLEMENTED: Implemented = Implemented::Both;
+
 pub fn parsing(input: &BString) -> color_eyre::Result<Parsed> {
     input
         .lines()
@@ -86,73 +96,303 @@ impl Rope {
         }
     }
 
-    #[allow(dead_code)]
-    fn display_in(&self, (x_min, x_max): (isize, isize), (y_min, y_max): (isize, isize)) {
-        for y in (y_min..=y_max).rev() {
-            for x in x_min..=x_max {
-                let segment = self
-                    .segments
-                    .iter()
-                    .enumerate()
-                    .find(|(_, &i)| i == (x, y))
-                    .map(|(i, _)| i);
-                match segment {
-                    Some(0) => print!("H"),
-                    Some(i) if i == self.segments.len() - 1 => print!("T"),
-                    Some(x) => print!("{x}"),
-                    None => print!("."),
-                }
-            }
-            println!()
+    /// The rope over the visited tail positions, rows are flipped so up is up
+    fn frame(&self, visited_tails: &FnvHashSet<(isize, isize)>, caption: String) -> Frame {
+        let mut frame = Frame::new(caption).with_background('.');
+        for &(x, y) in visited_tails {
+            frame.set(x as _, -y as _, '#');
+        }
+
+        // Draw the tail first so the segments closer to the head are on top
+        for (i, &(x, y)) in self.segments.iter().enumerate().rev() {
+            let c = match i {
+                0 => 'H',
+                i if i == self.segments.len() - 1 => 'T',
+                i => char::from_digit(i as u32, 36).unwrap_or('*'),
+            };
+            frame.set(x as _, -y as _, c);
         }
+
+        let (x, y) = self.segments[0];
+        frame.focus(x as _, -y as _);
+        frame
     }
 }
 
-pub fn part1(input: Parsed) {
-    let mut rope = Rope::new(2);
-    let mut visited_tails = HashSet::new();
-    visited_tails.insert(*rope.segments.last().unwrap());
-    for (dir, amount) in input {
-        for _ in 0..amount {
-            rope.move_dir(dir);
-            visited_tails.insert(*rope.segments.last().unwrap());
+/// Positions of the knots after each step, starting with the initial positions
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+    pub knots: usize,
+    pub steps: Vec<Vec<(isize, isize)>>,
+}
+
+impl Trace {
+    /// One `step,knot,x,y` line per knot and step, the head being knot 0
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,knot,x,y\n");
+        for (step, knots) in self.steps.iter().enumerate() {
+            for (knot, (x, y)) in knots.iter().enumerate() {
+                writeln!(csv, "{step},{knot},{x},{y}").unwrap();
+            }
         }
+        csv
     }
 
-    print_res!("Total positions visited: {}", visited_tails.len());
+    /// Writes the trace to `path`, as CSV or JSON depending on its extension
+    pub fn export(&self, path: &Path) -> color_eyre::Result<()> {
+        let content = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => self.to_csv(),
+            Some("json") => serde_json::to_string(self)?,
+            _ => eyre::bail!("Can't guess the trace format of {path:?}, use .csv or .json"),
+        };
+        Ok(std::fs::write(path, content)?)
+    }
 }
 
-pub fn part2(input: Parsed) {
-    let mut rope = Rope::new(10);
-    let mut visited_tails = HashSet::new();
-    visited_tails.insert(*rope.segments.last().unwrap());
-    for (dir, amount) in input {
+#[derive(Debug)]
+pub struct Simulation {
+    /// Number of distinct positions visited by each knot, from the head to the tail
+    pub visited: Vec<usize>,
+    pub trace: Option<Trace>,
+}
+
+/// Pulls a rope of `knots` knots along the moves, keeping every step if `trace` is set
+pub fn simulate(moves: &[(Direction, usize)], knots: usize, trace: bool) -> Simulation {
+    assert!(knots > 0, "A rope needs at least one knot");
+
+    let mut rope = Rope::new(knots);
+    let mut visited: Vec<FnvHashSet<_>> = rope
+        .segments
+        .iter()
+        .map(|&knot| FnvHashSet::from_iter([knot]))
+        .collect();
+    let mut trace = trace.then(|| Trace {
+        knots,
+        steps: vec![rope.segments.clone()],
+    });
+
+    for &(dir, amount) in moves {
         for _ in 0..amount {
             rope.move_dir(dir);
-            visited_tails.insert(*rope.segments.last().unwrap());
+            for (visited, &knot) in visited.iter_mut().zip(&rope.segments) {
+                visited.insert(knot);
+            }
+            if let Some(trace) = &mut trace {
+                trace.steps.push(rope.segments.clone());
+            }
+            visualize::frame(|| {
+                rope.frame(
+                    visited.last().unwrap(),
+                    format!("{knots} knots: {dir:?} {amount}"),
+                )
+            });
         }
     }
 
-    print_res!("Total positions visited: {}", visited_tails.len());
+    let visited: Vec<_> = visited.iter().map(|v| v.len()).collect();
+    debug!("Positions visited by each of the {knots} knots: {visited:?}");
+
+    Simulation { visited, trace }
+}
+
+pub fn part1(input: Parsed) {
+    let visited = simulate(&input, 2, false).visited;
+    print_res!("Total positions visited: {}", visited.last().unwrap());
+}
+
+pub fn part2(input: Parsed) {
+    let visited = simulate(&input, 10, false).visited;
+    print_res!("Total positions visited: {}", visited.last().unwrap());
 }
 
 pub fn main() -> color_eyre::Result<()> {
-    let context = load()?;
+    let context = load(IMPLEMENTED)?;
 
     let start = Instant::now();
     let parsed = parsing(&context.input)?;
-    let elapsed = humantime::format_duration(start.elapsed());
+    let elapsed = start.elapsed();
 
-    let start = Instant::now();
-    if context.part == 1 {
-        part1(parsed);
-    } else {
-        part2(parsed);
+    // The trace follows the rope of part 2 unless only part 1 is run
+    if let Some(path) = context.trace {
+        let knots = if context.part == Part::One { 2 } else { 10 };
+        simulate(&parsed, knots, true)
+            .trace
+            .unwrap()
+            .export(&path)?;
     }
-    let elapsed_part = humantime::format_duration(start.elapsed());
 
-    println!("  Parsing: {elapsed}");
-    println!("  Solving: {elapsed_part}");
+    run_parts(context.part, elapsed, parsed, part1, part2);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{parsing, part1, part2, simulate, Parsed};
+    use crate::output::capture;
+    use indoc::indoc;
+
+    const SMALL: &str = indoc! {"
+        R 4
+        U 4
+        L 3
+        D 1
+        R 4
+        D 1
+        L 5
+        R 2
+    "};
+
+    const LARGE: &str = indoc! {"
+        R 5
+        U 8
+        L 8
+        D 3
+        R 17
+        D 10
+        L 25
+        U 20
+    "};
+
+    /// Knot positions at the end of each move
+    fn states(input: &Parsed, knots: usize) -> Vec<Vec<(isize, isize)>> {
+        let steps = simulate(input, knots, true).trace.unwrap().steps;
+        input
+            .iter()
+            .scan(0, |step, &(_, amount)| {
+                *step += amount;
+                Some(steps[*step].clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn small_example() {
+        let input = parsing(&SMALL.as_bytes().to_vec().into()).unwrap();
+        assert_eq!(
+            states(&input, 2),
+            [
+                [(4, 0), (3, 0)],
+                [(4, 4), (4, 3)],
+                [(1, 4), (2, 4)],
+                [(1, 3), (2, 4)],
+                [(5, 3), (4, 3)],
+                [(5, 2), (4, 3)],
+                [(0, 2), (1, 2)],
+                [(2, 2), (1, 2)],
+            ]
+        );
+
+        assert_eq!(
+            capture(|| part1(input.clone())).1,
+            "Total positions visited: 13\n"
+        );
+        assert_eq!(capture(|| part2(input)).1, "Total positions visited: 1\n");
+    }
+
+    #[test]
+    fn large_example() {
+        let input = parsing(&LARGE.as_bytes().to_vec().into()).unwrap();
+        let states = states(&input, 10);
+        assert_eq!(
+            states[0],
+            [
+                (5, 0),
+                (4, 0),
+                (3, 0),
+                (2, 0),
+                (1, 0),
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (0, 0)
+            ]
+        );
+        assert_eq!(
+            states[1],
+            [
+                (5, 8),
+                (5, 7),
+                (5, 6),
+                (5, 5),
+                (5, 4),
+                (4, 4),
+                (3, 3),
+                (2, 2),
+                (1, 1),
+                (0, 0)
+            ]
+        );
+        assert_eq!(
+            states[2],
+            [
+                (-3, 8),
+                (-2, 8),
+                (-1, 8),
+                (0, 8),
+                (1, 8),
+                (1, 7),
+                (1, 6),
+                (1, 5),
+                (1, 4),
+                (1, 3)
+            ]
+        );
+        assert_eq!(
+            states[3],
+            [
+                (-3, 5),
+                (-3, 6),
+                (-2, 7),
+                (-1, 7),
+                (0, 7),
+                (1, 7),
+                (1, 6),
+                (1, 5),
+                (1, 4),
+                (1, 3)
+            ]
+        );
+        assert_eq!(
+            states[7],
+            (0..10).map(|i| (-11, 15 - i)).collect::<Vec<_>>()
+        );
+
+        assert_eq!(capture(|| part2(input)).1, "Total positions visited: 36\n");
+    }
+
+    #[test]
+    fn visited_per_knot() {
+        let input = parsing(&LARGE.as_bytes().to_vec().into()).unwrap();
+        let visited = simulate(&input, 10, false).visited;
+        assert_eq!(visited.len(), 10);
+        assert_eq!(visited[9], 36);
+        // Each knot only moves after the one before it
+        assert!(visited.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn trace_export() {
+        let input = parsing(&b"R 2\nU 1\n".to_vec().into()).unwrap();
+        let trace = simulate(&input, 2, true).trace.unwrap();
+        assert_eq!(
+            trace.to_csv(),
+            indoc! {"
+                step,knot,x,y
+                0,0,0,0
+                0,1,0,0
+                1,0,1,0
+                1,1,0,0
+                2,0,2,0
+                2,1,1,0
+                3,0,2,1
+                3,1,1,0
+            "}
+        );
+        assert_eq!(
+            serde_json::to_string(&trace).unwrap(),
+            r#"{"knots":2,"steps":[[[0,0],[0,0]],[[1,0],[0,0]],[[2,0],[1,0]],[[2,1],[1,0]]]}"#
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Frame by frame terminal animation of the simulations.
+//!
+//! Days build a [`Frame`] at each step of their simulation and hand it to
+//! [`frame`], which only calls the builder when running with `--visualize` or
+//! when recording the frames to export them with [`render`](crate::render).
+//! Results printed while the animation is running are shown once it ends.
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyModifiers},
+    queue, style, terminal,
+};
+use fnv::FnvHashMap;
+
+use crate::{
+    output::{self, Sink},
+    render::{self, Palette, DEFAULT_PALETTE},
+};
+
+/// A sparse grid of characters, the viewport is fitted to the cells that are set
+#[derive(Debug, Clone)]
+pub struct Frame {
+    cells: FnvHashMap<(i64, i64), char>,
+    bounds: Option<((i64, i64), (i64, i64))>,
+    background: char,
+    palette: Palette,
+    focus: Option<(i64, i64)>,
+    caption: String,
+}
+
+impl Frame {
+    pub fn new(caption: impl Into<String>) -> Self {
+        Self {
+            cells: FnvHashMap::default(),
+            bounds: None,
+            background: ' ',
+            palette: DEFAULT_PALETTE,
+            focus: None,
+            caption: caption.into(),
+        }
+    }
+
+    /// Character drawn in the cells that are not set
+    pub fn with_background(mut self, background: char) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Colors of the characters when rendering the frame to an image
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Point kept in view when the frame does not fit in the terminal
+    pub fn focus(&mut self, x: i64, y: i64) {
+        self.focus = Some((x, y));
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, c: char) {
+        self.cells.insert((x, y), c);
+        self.bounds = Some(match self.bounds {
+            None => ((x, y), (x, y)),
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+            }
+        });
+    }
+
+    /// Sets every character of `text`, one line per row, starting at `(x, y)`
+    pub fn set_text(&mut self, x: i64, y: i64, text: &str) {
+        for (dy, line) in text.lines().enumerate() {
+            for (dx, c) in line.chars().enumerate() {
+                self.set(x + dx as i64, y + dy as i64, c);
+            }
+        }
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> char {
+        self.cells.get(&(x, y)).copied().unwrap_or(self.background)
+    }
+
+    /// Top left and bottom right corners of the cells that are set
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        self.bounds
+    }
+
+    pub fn background(&self) -> char {
+        self.background
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub fn caption(&self) -> &str {
+        &self.caption
+    }
+
+    fn row(&self, y: i64, x: i64, width: i64) -> String {
+        (x..x + width).map(|x| self.get(x, y)).collect()
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(((min_x, min_y), (max_x, max_y))) = self.bounds else {
+            return Ok(());
+        };
+
+        for y in min_y..=max_y {
+            writeln!(f, "{}", self.row(y, min_x, max_x - min_x + 1))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Frames are never drawn faster than this, faster speeds skip frames
+const MAX_DRAW_RATE: f64 = 60.;
+const MAX_SPEED: f64 = 1e6;
+
+struct Player {
+    fps: f64,
+    paused: bool,
+    received: u64,
+    last_draw: Instant,
+    started: bool,
+    quit: bool,
+    previous_sink: Option<Sink>,
+}
+
+/// Start of the viewport along one axis, showing the whole range if it fits
+/// and keeping `focus` in the middle otherwise
+fn fit(min: i64, max: i64, size: i64, focus: Option<i64>) -> i64 {
+    if max - min < size {
+        return min;
+    }
+
+    match focus {
+        Some(focus) => (focus - size / 2).clamp(min, max - size + 1),
+        None => min,
+    }
+}
+
+impl Player {
+    /// Frames received for each frame drawn
+    fn stride(&self) -> u64 {
+        (self.fps / MAX_DRAW_RATE).ceil().max(1.) as u64
+    }
+
+    fn delay(&self) -> Duration {
+        Duration::from_secs_f64(self.stride() as f64 / self.fps)
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        queue!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        self.started = true;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        if self.started {
+            queue!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)?;
+            io::stdout().flush()?;
+            terminal::disable_raw_mode()?;
+            self.started = false;
+        }
+        Ok(())
+    }
+
+    fn draw(&self, frame: &Frame, status: &str) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let (width, height) = (cols as i64, rows.saturating_sub(1) as i64);
+        let mut stdout = io::stdout().lock();
+
+        queue!(stdout, cursor::MoveTo(0, 0))?;
+        if let Some(((min_x, min_y), (max_x, max_y))) = frame.bounds() {
+            let x = fit(min_x, max_x, width, frame.focus.map(|f| f.0));
+            let y = fit(min_y, max_y, height, frame.focus.map(|f| f.1));
+            for row in 0..height.min(max_y - min_y + 1) {
+                queue!(
+                    stdout,
+                    style::Print(frame.row(y + row, x, width.min(max_x - min_x + 1))),
+                    terminal::Clear(terminal::ClearType::UntilNewLine),
+                    cursor::MoveToNextLine(1),
+                )?;
+            }
+        }
+
+        queue!(stdout, terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        drop(stdout);
+        self.draw_status(status)
+    }
+
+    fn draw_status(&self, status: &str) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let status: String = status.chars().take(cols as usize).collect();
+
+        let mut stdout = io::stdout().lock();
+        queue!(
+            stdout,
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            style::Print(status),
+        )?;
+        stdout.flush()
+    }
+
+    fn status(&self, frame: &Frame) -> String {
+        format!(
+            "{} | frame {} | {}{} fps | space: pause, n: step, +/-: speed, q: quit",
+            frame.caption(),
+            self.received,
+            if self.paused { "paused, " } else { "" },
+            self.fps,
+        )
+    }
+
+    /// Draws `frame` and handles the controls until the next frame is due,
+    /// returns `false` if the animation was stopped
+    fn show(&mut self, frame: &Frame) -> io::Result<bool> {
+        if !self.started {
+            self.start()?;
+        }
+        self.draw(frame, &self.status(frame))?;
+
+        let due = self.last_draw + self.delay();
+        loop {
+            let timeout = match self.paused {
+                true => None,
+                false => match due.checked_duration_since(Instant::now()) {
+                    None => break,
+                    Some(timeout) => Some(timeout),
+                },
+            };
+
+            if let Some(timeout) = timeout {
+                if !event::poll(timeout)? {
+                    break;
+                }
+            }
+
+            match event::read()? {
+                Event::Key(key) => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(false)
+                    }
+                    KeyCode::Char(' ') => self.paused = !self.paused,
+                    KeyCode::Char('n') | KeyCode::Right if self.paused => break,
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        self.fps = (self.fps * 2.).min(MAX_SPEED)
+                    }
+                    KeyCode::Char('-') => self.fps = (self.fps / 2.).max(0.5),
+                    _ => (),
+                },
+                Event::Resize(..) => (),
+                _ => continue,
+            }
+
+            self.draw(frame, &self.status(frame))?;
+        }
+
+        self.last_draw = Instant::now();
+        Ok(true)
+    }
+
+    fn wait_key(&self) -> io::Result<()> {
+        loop {
+            if let Event::Key(_) = event::read()? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+struct Export {
+    path: PathBuf,
+    scale: u32,
+    fps: f64,
+}
+
+thread_local! {
+    static PLAYER: RefCell<Option<Player>> = const { RefCell::new(None) };
+    static RECORDING: RefCell<Option<Vec<Frame>>> = const { RefCell::new(None) };
+    static EXPORT: RefCell<Option<Export>> = const { RefCell::new(None) };
+}
+
+/// Animates the frames of this thread at `fps` frames per second
+pub fn enable(fps: f64) {
+    let previous_sink = output::set_sink(Sink::Collect(String::new()));
+
+    PLAYER.with(|player| {
+        *player.borrow_mut() = Some(Player {
+            fps,
+            paused: false,
+            received: 0,
+            last_draw: Instant::now(),
+            started: false,
+            quit: false,
+            previous_sink: Some(previous_sink),
+        })
+    });
+}
+
+/// Records the frames of this thread, they are written to `path` by [`finish`]
+pub fn export(path: PathBuf, scale: u32, fps: f64) {
+    EXPORT.with(|export| *export.borrow_mut() = Some(Export { path, scale, fps }));
+    RECORDING.with(|recording| *recording.borrow_mut() = Some(Vec::new()));
+}
+
+/// Runs `f`, collecting the frames it builds
+pub fn record<R>(f: impl FnOnce() -> R) -> (R, Vec<Frame>) {
+    let previous = RECORDING.with(|recording| recording.replace(Some(Vec::new())));
+    let res = f();
+    let frames = RECORDING.with(|recording| recording.replace(previous));
+    (res, frames.unwrap_or_default())
+}
+
+/// Whether frames are being animated or recorded, to skip work only needed by the frames
+pub fn enabled() -> bool {
+    RECORDING.with(|recording| recording.borrow().is_some())
+        || PLAYER.with(|player| player.borrow().as_ref().is_some_and(|p| !p.quit))
+}
+
+/// Shows or records the frame built by `build`, which is only called when needed
+pub fn frame(build: impl FnOnce() -> Frame) {
+    let recording = RECORDING.with(|recording| recording.borrow().is_some());
+    let play = PLAYER.with(|player| match player.borrow_mut().as_mut() {
+        Some(player) if !player.quit => {
+            player.received += 1;
+            player.paused || player.received % player.stride() == 0
+        }
+        _ => false,
+    });
+    if !recording && !play {
+        return;
+    }
+
+    let frame = build();
+
+    if play {
+        PLAYER.with(|player| {
+            let mut player = player.borrow_mut();
+            let player = player.as_mut().unwrap();
+            if let Err(e) = player.show(&frame).and_then(|next| {
+                if !next {
+                    player.quit = true;
+                    player.stop()?;
+                }
+                Ok(())
+            }) {
+                player.quit = true;
+                let _ = player.stop();
+                eprintln!("Stopped visualization: {e}");
+            }
+        });
+    }
+
+    if recording {
+        RECORDING.with(|recording| recording.borrow_mut().as_mut().unwrap().push(frame));
+    }
+}
+
+/// Waits for a key on the last frame, restores the terminal and prints the
+/// results collected during the animation, then writes the exported frames
+pub fn finish() {
+    if let Some(mut player) = PLAYER.with(|player| player.borrow_mut().take()) {
+        if player.started {
+            let res = player
+                .draw_status("done, press any key to exit")
+                .and_then(|_| player.wait_key())
+                .and_then(|_| player.stop());
+            if let Err(e) = res {
+                eprintln!("Could not restore the terminal: {e}");
+            }
+        }
+
+        if let Some(Sink::Collect(results)) = player.previous_sink.map(output::set_sink) {
+            output::write(format_args!("{results}"));
+        }
+    }
+
+    if let Some(export) = EXPORT.with(|export| export.borrow_mut().take()) {
+        let frames = RECORDING
+            .with(|recording| recording.borrow_mut().take())
+            .unwrap_or_default();
+        match render::export(&export.path, &frames, export.scale, export.fps) {
+            Ok(()) => eprintln!("==> Wrote {} frames to {:?}", frames.len(), export.path),
+            Err(e) => eprintln!("Could not export the frames: {e:?}"),
+        }
+    }
+}
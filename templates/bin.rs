@@ -0,0 +1,3 @@
+fn main() -> color_eyre::Result<()> {
+    problems::solutions::{{module}}::main()
+}